@@ -1,14 +1,13 @@
 use crate::expr::Expr;
+use crate::stack;
+use crate::stmt::Stmt;
 
 pub struct AstPrinter;
 
 impl AstPrinter {
     pub fn pretty_print(e: &Expr) -> String {
-        match *e {
-            Expr::Literal(ref l) => match l {
-                Some(v) => format!("{}", v),
-                None => "None".to_string(),
-            },
+        stack::guard(|| match *e {
+            Expr::Literal(_, ref l) => format!("{}", l),
             Expr::Logical(ref lhs, ref token, ref rhs) => format!(
                 "({} {} {})",
                 Self::pretty_print(lhs),
@@ -21,14 +20,175 @@ impl AstPrinter {
                 Self::pretty_print(lhs),
                 Self::pretty_print(rhs)
             ),
+            Expr::Call(ref callee, _, ref args) => {
+                let args: Vec<String> = args.iter().map(Self::pretty_print).collect();
+
+                format!("(call {} {})", Self::pretty_print(callee), args.join(" "))
+            }
+            Expr::Compound(ref target, ref token, ref value) => format!(
+                "({} {} {})",
+                token.lexeme(),
+                Self::pretty_print(target),
+                Self::pretty_print(value)
+            ),
+            Expr::Conditional(ref cond, ref then_e, ref else_e) => format!(
+                "(?: {} {} {})",
+                Self::pretty_print(cond),
+                Self::pretty_print(then_e),
+                Self::pretty_print(else_e)
+            ),
+            Expr::Get(ref object, ref name) => {
+                format!("(. {} {})", Self::pretty_print(object), name.lexeme())
+            }
+            Expr::Set(ref object, ref name, ref value) => format!(
+                "(= (. {} {}) {})",
+                Self::pretty_print(object),
+                name.lexeme(),
+                Self::pretty_print(value)
+            ),
+            Expr::Index(ref object, _, ref index) => format!(
+                "([] {} {})",
+                Self::pretty_print(object),
+                Self::pretty_print(index)
+            ),
+            Expr::List(_, ref elements) => {
+                let elements: Vec<String> = elements.iter().map(Self::pretty_print).collect();
+
+                format!("(list {})", elements.join(" "))
+            }
+            Expr::Map(_, ref entries) => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(k, v)| format!("({} {})", Self::pretty_print(k), Self::pretty_print(v)))
+                    .collect();
+
+                format!("(map {})", entries.join(" "))
+            }
+            Expr::Postfix(ref target, ref token) => {
+                format!("(post{} {})", token.lexeme(), Self::pretty_print(target))
+            }
+            Expr::SetIndex(ref object, _, ref index, ref value) => format!(
+                "(= ([] {} {}) {})",
+                Self::pretty_print(object),
+                Self::pretty_print(index),
+                Self::pretty_print(value)
+            ),
+            Expr::Stringify(ref expr) => format!("(str {})", Self::pretty_print(expr)),
+            Expr::Super(_, ref method, _) => format!("(super {})", method.lexeme()),
+            Expr::This(..) => "this".to_string(),
             Expr::Grouping(ref expr) => format!("(group {})", Self::pretty_print(expr)),
             Expr::Unary(ref token, ref expr) => {
                 format!("({} {})", token.lexeme(), Self::pretty_print(expr))
             }
-            Expr::Var(ref token) => format!("var {}", token.lexeme()),
-            Expr::Assign(ref token, ref expr) => {
+            Expr::Var(ref token, _) => format!("var {}", token.lexeme()),
+            Expr::Assign(ref token, ref expr, _) => {
                 format!("({} = {})", token.lexeme(), Self::pretty_print(expr))
             }
+        })
+    }
+}
+
+impl AstPrinter {
+    pub fn print_stmts(statements: &[Stmt]) -> String {
+        let lines: Vec<String> = statements.iter().map(|s| Self::print_stmt(s, 0)).collect();
+
+        lines.join("\n")
+    }
+
+    fn print_stmt(s: &Stmt, depth: usize) -> String {
+        stack::guard(|| match *s {
+            Stmt::Block(ref statements) => Self::print_body("(block", statements.iter(), depth),
+            Stmt::Break(_) => "(break)".to_string(),
+            Stmt::Class(ref name, ref superclass, ref methods) => {
+                let head: String = match superclass {
+                    Some(s) => format!("(class {} < {}", name.lexeme(), Self::pretty_print(s)),
+                    None => format!("(class {}", name.lexeme()),
+                };
+
+                Self::print_body(&head, methods.iter(), depth)
+            }
+            Stmt::Continue(_) => "(continue)".to_string(),
+            Stmt::Expr(ref e) => format!("(; {})", Self::pretty_print(e)),
+            Stmt::Function(ref name, ref params, ref body) => {
+                let params: Vec<&str> = params.iter().map(|p| p.lexeme()).collect();
+                let head: String = format!("(fun {} ({})", name.lexeme(), params.join(" "));
+
+                Self::print_body(&head, body.iter(), depth)
+            }
+            Stmt::If(ref cond, ref then_s, ref else_s) => {
+                let head: String = format!("(if {}", Self::pretty_print(cond));
+                let branches = [Some(then_s), else_s.as_ref()].into_iter().flatten();
+
+                Self::print_body(&head, branches.map(|b| &**b), depth)
+            }
+            Stmt::Import(ref path, ref name) => {
+                format!("(import {} {})", path.lexeme(), name.lexeme())
+            }
+            Stmt::Print(ref e) => format!("(print {})", Self::pretty_print(e)),
+            Stmt::Return(_, ref value) => match value {
+                Some(v) => format!("(return {})", Self::pretty_print(v)),
+                None => "(return)".to_string(),
+            },
+            Stmt::Throw(_, ref value) => format!("(throw {})", Self::pretty_print(value)),
+            Stmt::Try(ref body, ref catch, ref finally) => {
+                let indent: String = "  ".repeat(depth + 1);
+                let mut out: String = "(try".to_string();
+
+                out += &format!(
+                    "\n{}{}",
+                    indent,
+                    Self::print_body("(block", body.iter(), depth + 1)
+                );
+                if let Some((ref name, ref handler)) = *catch {
+                    let head: String = format!("(catch {}", name.lexeme());
+                    out += &format!(
+                        "\n{}{}",
+                        indent,
+                        Self::print_body(&head, handler.iter(), depth + 1)
+                    );
+                }
+                if let Some(ref finally) = *finally {
+                    out += &format!(
+                        "\n{}{}",
+                        indent,
+                        Self::print_body("(finally", finally.iter(), depth + 1)
+                    );
+                }
+
+                out + ")"
+            }
+            Stmt::Var(ref name, ref init) => match init {
+                Some(i) => format!("(var {} {})", name.lexeme(), Self::pretty_print(i)),
+                None => format!("(var {})", name.lexeme()),
+            },
+            Stmt::While(ref cond, ref body, ref increment) => {
+                let head: String = match increment {
+                    Some(inc) => format!(
+                        "(while {} {}",
+                        Self::pretty_print(cond),
+                        Self::pretty_print(inc)
+                    ),
+                    None => format!("(while {}", Self::pretty_print(cond)),
+                };
+
+                Self::print_body(&head, [&**body], depth)
+            }
+        })
+    }
+
+    // Puts each statement of a body on a line of its own, one level deeper.
+    fn print_body<'a>(
+        head: &str,
+        body: impl IntoIterator<Item = &'a Stmt>,
+        depth: usize,
+    ) -> String {
+        let indent: String = "  ".repeat(depth + 1);
+        let mut out: String = head.to_string();
+
+        for s in body {
+            out += &format!("\n{}{}", indent, Self::print_stmt(s, depth + 1));
         }
+
+        out + ")"
     }
 }
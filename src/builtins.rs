@@ -0,0 +1,239 @@
+use crate::environment::Environment;
+use crate::error::Exception;
+use crate::token::Token;
+use crate::value::{Class, Instance, Key, List, Map, Native, NativeFn, Value};
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn natives() -> Vec<Native> {
+    vec![Native {
+        name: "clock".to_string(),
+        arity: 0,
+        fun: Box::new(|_| {
+            let now: f64 = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_err(|e| e.to_string())?
+                .as_secs_f64();
+
+            Ok(Value::Number(now))
+        }),
+    }]
+}
+
+// The main script and every module start out with their own copy of the
+// registered natives, so redefining one in a module doesn't leak elsewhere.
+pub fn globals(natives: &[Rc<Native>]) -> Environment {
+    let mut env: Environment = Environment::new();
+    for native in natives {
+        env.define(native.name.clone(), Value::Native(native.clone()));
+    }
+
+    env
+}
+
+// A thrown value is caught as is, while errors raised by the runtime itself
+// are caught as `Error` instances carrying their message and line.
+pub fn catch(e: &Exception) -> Value {
+    if let Some(value) = e.value() {
+        return value.clone();
+    }
+
+    let class: Class = Class {
+        name: "Error".to_string(),
+        methods: RefCell::new(HashMap::new()),
+    };
+    let fields: HashMap<String, Value> = HashMap::from([
+        ("message".to_string(), Value::Str(Rc::from(e.msg()))),
+        ("line".to_string(), Value::Number(e.token().line() as f64)),
+    ]);
+
+    Value::Instance(Rc::new(Instance {
+        class: Rc::new(class),
+        fields: RefCell::new(fields),
+    }))
+}
+
+// Rethrowing a caught `Error` keeps its original message.
+pub fn throw(value: Value, token: Token) -> Exception {
+    let msg: String = match value {
+        Value::Instance(ref instance) if instance.class.name == "Error" => {
+            match instance.fields.borrow().get("message") {
+                Some(message) => message.to_string(),
+                None => value.to_string(),
+            }
+        }
+        _ => value.to_string(),
+    };
+
+    Exception::thrown(msg, token, value)
+}
+
+pub fn property(object: &Value, name: &str) -> Result<Value, String> {
+    match *object {
+        Value::List(ref list) => list_method(list, name),
+        Value::Map(ref map) => map_method(map, name),
+        Value::Module(ref module) => module
+            .globals
+            .borrow()
+            .get(name)
+            .ok_or_else(|| format!("Undefined property '{}'", name)),
+        _ => Err("Only instances have properties".to_string()),
+    }
+}
+
+pub fn get_index(object: &Value, index: &Value) -> Result<Value, String> {
+    match *object {
+        Value::List(ref list) => {
+            let list: Ref<List> = list.borrow();
+            let idx: usize = list_index(index, list.len())?;
+
+            Ok(list[idx].clone())
+        }
+        Value::Map(ref map) => {
+            let key: Key = map_key(index)?;
+
+            map.borrow()
+                .get(&key)
+                .cloned()
+                .ok_or_else(|| format!("Undefined key '{}'", index))
+        }
+        _ => Err("Only lists and maps can be indexed".to_string()),
+    }
+}
+
+pub fn set_index(object: &Value, index: &Value, value: Value) -> Result<(), String> {
+    match *object {
+        Value::List(ref list) => {
+            let mut list: RefMut<List> = list.borrow_mut();
+            let idx: usize = list_index(index, list.len())?;
+
+            list[idx] = value;
+            Ok(())
+        }
+        Value::Map(ref map) => {
+            map.borrow_mut().insert(map_key(index)?, value);
+            Ok(())
+        }
+        _ => Err("Only lists and maps can be indexed".to_string()),
+    }
+}
+
+// `%` and `~/` round towards negative infinity so that
+// `a == (a ~/ b) * b + a % b` holds for either sign.
+pub fn modulo(l: f64, r: f64) -> f64 {
+    let rem: f64 = l % r;
+
+    if rem != 0.0 && (rem < 0.0) != (r < 0.0) {
+        rem + r
+    } else {
+        rem
+    }
+}
+
+pub fn floor_divide(l: f64, r: f64) -> f64 {
+    (l / r).floor()
+}
+
+pub fn map_key(key: &Value) -> Result<Key, String> {
+    Key::new(key.clone()).ok_or_else(|| "Map keys must be numbers, strings or booleans".to_string())
+}
+
+fn list_index(index: &Value, len: usize) -> Result<usize, String> {
+    match *index {
+        Value::Number(n) if n.fract() == 0.0 => {
+            if n < 0.0 || n >= len as f64 {
+                return Err("Index out of bounds".to_string());
+            }
+
+            Ok(n as usize)
+        }
+        _ => Err("Index must be an integer".to_string()),
+    }
+}
+
+fn list_method(list: &Rc<RefCell<List>>, name: &str) -> Result<Value, String> {
+    let list: Rc<RefCell<List>> = list.clone();
+
+    let (arity, fun): (usize, NativeFn) = match name {
+        "length" => (
+            0,
+            Box::new(move |_| Ok(Value::Number(list.borrow().len() as f64))),
+        ),
+        "push" => (
+            1,
+            Box::new(move |args| {
+                list.borrow_mut().push(args[0].clone());
+                Ok(Value::Nil)
+            }),
+        ),
+        "pop" => (
+            0,
+            Box::new(move |_| {
+                list.borrow_mut()
+                    .pop()
+                    .ok_or_else(|| "Can't pop from an empty list".to_string())
+            }),
+        ),
+        "insert" => (
+            2,
+            Box::new(move |args| {
+                let mut list: RefMut<List> = list.borrow_mut();
+                let idx: usize = list_index(&args[0], list.len() + 1)?;
+
+                list.insert(idx, args[1].clone());
+                Ok(Value::Nil)
+            }),
+        ),
+        _ => return Err(format!("Undefined property '{}'", name)),
+    };
+
+    Ok(Value::Native(Rc::new(Native {
+        name: name.to_string(),
+        arity,
+        fun,
+    })))
+}
+
+fn map_method(map: &Rc<RefCell<Map>>, name: &str) -> Result<Value, String> {
+    let map: Rc<RefCell<Map>> = map.clone();
+
+    let (arity, fun): (usize, NativeFn) = match name {
+        "length" => (
+            0,
+            Box::new(move |_| Ok(Value::Number(map.borrow().len() as f64))),
+        ),
+        "has" => (
+            1,
+            Box::new(move |args| Ok(Value::Bool(map.borrow().contains(&map_key(&args[0])?)))),
+        ),
+        "delete" => (
+            1,
+            Box::new(move |args| {
+                let removed: Option<Value> = map.borrow_mut().remove(&map_key(&args[0])?);
+
+                Ok(Value::Bool(removed.is_some()))
+            }),
+        ),
+        "keys" => (
+            0,
+            Box::new(move |_| {
+                let keys: Vec<Value> = map
+                    .borrow()
+                    .iter()
+                    .map(|(k, _)| k.value().clone())
+                    .collect();
+
+                Ok(Value::List(Rc::new(RefCell::new(List::from(keys)))))
+            }),
+        ),
+        _ => return Err(format!("Undefined property '{}'", name)),
+    };
+
+    Ok(Value::Native(Rc::new(Native {
+        name: name.to_string(),
+        arity,
+        fun,
+    })))
+}
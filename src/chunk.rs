@@ -0,0 +1,130 @@
+use crate::token::Token;
+use crate::value::{Proto, Value};
+use std::rc::Rc;
+
+#[derive(Copy, Clone, Debug)]
+pub enum OpCode {
+    Constant(u16),
+    Nil,
+    True,
+    False,
+    Pop,
+    Dup(u8),
+    Bury(u8),
+    GetLocal(u8),
+    SetLocal(u8),
+    GetGlobal(u16),
+    DefineGlobal(u16),
+    SetGlobal(u16),
+    GetUpvalue(u8),
+    SetUpvalue(u8),
+    GetProperty(u16),
+    SetProperty(u16),
+    GetSuper(u16),
+    GetIndex,
+    SetIndex,
+    List(u16),
+    Map(u16),
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    FloorDivide,
+    Power,
+    Not,
+    Stringify,
+    Negate,
+    Print,
+    Jump(u32),
+    JumpIfFalse(u32),
+    Call(u8),
+    Closure(u16),
+    CloseUpvalue,
+    Return,
+    PushCatch(u32),
+    PushFinally(u32),
+    PopHandler,
+    Throw,
+    Rethrow,
+    Import(u16),
+    Class(u16),
+    Inherit,
+    Method(u16),
+}
+
+pub struct Chunk {
+    code: Vec<OpCode>,
+    constants: Vec<Value>,
+    protos: Vec<Rc<Proto>>,
+    tokens: Vec<(usize, Token)>,
+}
+
+impl Chunk {
+    pub fn new() -> Chunk {
+        Chunk {
+            code: Vec::new(),
+            constants: Vec::new(),
+            protos: Vec::new(),
+            tokens: Vec::new(),
+        }
+    }
+
+    pub fn write(&mut self, op: OpCode) -> usize {
+        self.code.push(op);
+
+        self.code.len() - 1
+    }
+
+    // Only instructions that can fail at runtime record the token they came
+    // from; `token` finds the closest one at or before a given offset.
+    pub fn write_at(&mut self, op: OpCode, token: &Token) -> usize {
+        self.tokens.push((self.code.len(), token.clone()));
+
+        self.write(op)
+    }
+
+    pub fn patch(&mut self, offset: usize, op: OpCode) {
+        self.code[offset] = op;
+    }
+
+    pub fn len(&self) -> usize {
+        self.code.len()
+    }
+
+    pub fn code(&self, offset: usize) -> OpCode {
+        self.code[offset]
+    }
+
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        self.constants.push(value);
+
+        self.constants.len() - 1
+    }
+
+    pub fn constant(&self, idx: u16) -> &Value {
+        &self.constants[idx as usize]
+    }
+
+    pub fn add_proto(&mut self, proto: Rc<Proto>) -> usize {
+        self.protos.push(proto);
+
+        self.protos.len() - 1
+    }
+
+    pub fn proto(&self, idx: u16) -> Rc<Proto> {
+        self.protos[idx as usize].clone()
+    }
+
+    pub fn token(&self, offset: usize) -> Option<&Token> {
+        let idx: usize = self.tokens.partition_point(|(o, _)| *o <= offset);
+
+        self.tokens.get(idx.checked_sub(1)?).map(|(_, t)| t)
+    }
+}
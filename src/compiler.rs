@@ -0,0 +1,882 @@
+use crate::chunk::{Chunk, OpCode};
+use crate::error::CompileError;
+use crate::expr::Expr;
+use crate::stack;
+use crate::stmt::Stmt;
+use crate::token::{Token, TokenType};
+use crate::value::{Proto, Value};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Copy, Clone, PartialEq)]
+enum FunctionType {
+    Script,
+    Function,
+    Initializer,
+    Method,
+}
+
+struct Local {
+    name: String,
+    depth: usize,
+    is_captured: bool,
+}
+
+struct Loop {
+    scope_depth: usize,
+    tries: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+// An active exception handler, and the `finally` block that has to run when
+// control leaves it by `return`, `break` or `continue`.
+#[derive(Clone)]
+struct Try {
+    scope_depth: usize,
+    finally: Option<Rc<Vec<Stmt>>>,
+}
+
+struct Frame {
+    name: String,
+    ftype: FunctionType,
+    arity: usize,
+    chunk: Chunk,
+    identifiers: HashMap<String, u16>,
+    locals: Vec<Local>,
+    upvalues: Vec<(bool, u8)>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+    tries: Vec<Try>,
+    // Limits already reported, so a chunk past one doesn't report it for
+    // every index after.
+    full: Vec<&'static str>,
+}
+
+impl Frame {
+    fn new(name: String, ftype: FunctionType) -> Frame {
+        let slot_zero: &str = match ftype {
+            FunctionType::Initializer | FunctionType::Method => "this",
+            _ => "",
+        };
+
+        Frame {
+            name,
+            ftype,
+            arity: 0,
+            chunk: Chunk::new(),
+            identifiers: HashMap::new(),
+            locals: vec![Local {
+                name: slot_zero.to_string(),
+                depth: 0,
+                is_captured: false,
+            }],
+            upvalues: Vec::new(),
+            scope_depth: 0,
+            loops: Vec::new(),
+            tries: Vec::new(),
+            full: Vec::new(),
+        }
+    }
+}
+
+pub struct Compiler {
+    frames: Vec<Frame>,
+    token: Token,
+    errors: Vec<CompileError>,
+}
+
+impl Compiler {
+    pub fn new() -> Compiler {
+        Compiler {
+            frames: Vec::new(),
+            token: Token::new(TokenType::Eof, "".to_string(), None, 0),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn compile(&mut self, statements: &[Stmt]) -> Result<Rc<Proto>, Vec<CompileError>> {
+        self.frames
+            .push(Frame::new("".to_string(), FunctionType::Script));
+        for s in statements {
+            self.statement(s);
+        }
+        self.emit_return();
+
+        let script: Rc<Proto> = Rc::new(self.end_frame());
+        if self.errors.is_empty() {
+            Ok(script)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn end_frame(&mut self) -> Proto {
+        let frame: Frame = self.frames.pop().unwrap();
+
+        Proto {
+            name: frame.name,
+            arity: frame.arity,
+            upvalues: frame.upvalues,
+            chunk: frame.chunk,
+        }
+    }
+
+    fn statement(&mut self, s: &Stmt) {
+        stack::guard(|| match *s {
+            Stmt::Block(ref statements) => self.block(statements),
+            Stmt::Break(_) => {
+                self.exit_loop_tries();
+                self.discard_loop_locals();
+
+                let jump: usize = self.emit(OpCode::Jump(0));
+                if let Some(l) = self.frame().loops.last_mut() {
+                    l.breaks.push(jump);
+                }
+            }
+            Stmt::Class(ref name, ref superclass, ref methods) => {
+                let name_const: u16 = self.identifier_constant(name);
+
+                self.declare_variable(name);
+                self.emit(OpCode::Class(name_const));
+                self.define_variable(name);
+
+                if let Some(Expr::Var(ref super_name, _)) = *superclass {
+                    self.named_variable(super_name, false);
+
+                    self.begin_scope();
+                    self.add_local("super", name);
+
+                    self.named_variable(name, false);
+                    self.emit_at(OpCode::Inherit, super_name);
+                }
+
+                self.named_variable(name, false);
+                for m in methods {
+                    if let Stmt::Function(ref m_name, ref params, ref body) = *m {
+                        let ftype: FunctionType = if m_name.lexeme() == "init" {
+                            FunctionType::Initializer
+                        } else {
+                            FunctionType::Method
+                        };
+
+                        self.function(m_name, params, body, ftype);
+
+                        let m_const: u16 = self.identifier_constant(m_name);
+                        self.emit(OpCode::Method(m_const));
+                    }
+                }
+                self.emit(OpCode::Pop);
+
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+            }
+            Stmt::Continue(_) => {
+                self.exit_loop_tries();
+                self.discard_loop_locals();
+
+                let jump: usize = self.emit(OpCode::Jump(0));
+                if let Some(l) = self.frame().loops.last_mut() {
+                    l.continues.push(jump);
+                }
+            }
+            Stmt::Expr(ref e) => {
+                self.expression(e);
+                self.emit(OpCode::Pop);
+            }
+            Stmt::Function(ref name, ref params, ref body) => {
+                self.declare_variable(name);
+                self.function(name, params, body, FunctionType::Function);
+                self.define_variable(name);
+            }
+            Stmt::If(ref cond, ref then_s, ref else_s) => {
+                self.expression(cond);
+
+                let then_jump: usize = self.emit(OpCode::JumpIfFalse(0));
+                self.emit(OpCode::Pop);
+                self.statement(then_s);
+
+                let else_jump: usize = self.emit(OpCode::Jump(0));
+                self.patch_jump(then_jump);
+                self.emit(OpCode::Pop);
+
+                if let Some(else_s) = else_s {
+                    self.statement(else_s);
+                }
+                self.patch_jump(else_jump);
+            }
+            Stmt::Import(ref path, ref name) => {
+                let idx: usize = self
+                    .frame()
+                    .chunk
+                    .add_constant(path.literal().clone().unwrap());
+                let idx: u16 = self.check_index(idx, path, "Too many constants in one chunk");
+
+                self.emit_at(OpCode::Import(idx), path);
+                self.declare_variable(name);
+                self.define_variable(name);
+            }
+            Stmt::Print(ref e) => {
+                self.expression(e);
+                self.emit(OpCode::Print);
+            }
+            Stmt::Return(ref keyword, ref value) => {
+                match value {
+                    Some(v) => self.expression(v),
+                    None if self.frame().ftype == FunctionType::Initializer => {
+                        self.emit(OpCode::GetLocal(0));
+                    }
+                    None => {
+                        self.emit(OpCode::Nil);
+                    }
+                }
+
+                if !self.frame().tries.is_empty() {
+                    // Keep the result out of reach of the `finally` blocks.
+                    self.begin_scope();
+                    self.add_local("", keyword);
+                    self.exit_tries(0);
+                    self.frame().locals.pop();
+                    self.frame().scope_depth -= 1;
+                }
+
+                self.emit(OpCode::Return);
+            }
+            Stmt::Throw(ref keyword, ref value) => {
+                self.expression(value);
+                self.emit_at(OpCode::Throw, keyword);
+            }
+            Stmt::Try(ref body, ref catch, ref finally) => {
+                let scope_depth: usize = self.frame().scope_depth;
+                let handler: usize = if catch.is_some() {
+                    self.emit(OpCode::PushCatch(0))
+                } else {
+                    self.emit(OpCode::PushFinally(0))
+                };
+
+                self.frame().tries.push(Try {
+                    scope_depth,
+                    finally: finally.clone(),
+                });
+                self.block(body);
+                self.frame().tries.pop();
+                self.emit(OpCode::PopHandler);
+
+                let mut exits: Vec<usize> = Vec::new();
+                if let Some(ref f) = *finally {
+                    self.block(f);
+                }
+                exits.push(self.emit(OpCode::Jump(0)));
+                self.patch_jump(handler);
+
+                if let Some((ref name, ref handler_body)) = *catch {
+                    // The VM pushes the caught value, which becomes the local.
+                    self.begin_scope();
+                    self.add_local(name.lexeme(), name);
+
+                    let rethrow: Option<usize> =
+                        finally.as_ref().map(|_| self.emit(OpCode::PushFinally(0)));
+                    if rethrow.is_some() {
+                        self.frame().tries.push(Try {
+                            scope_depth,
+                            finally: finally.clone(),
+                        });
+                    }
+                    for s in handler_body {
+                        self.statement(s);
+                    }
+                    if let (Some(rethrow), Some(f)) = (rethrow, finally.as_ref()) {
+                        self.frame().tries.pop();
+                        self.emit(OpCode::PopHandler);
+                        self.end_scope();
+
+                        self.block(f);
+                        exits.push(self.emit(OpCode::Jump(0)));
+
+                        // The caught value stays beneath the pending error,
+                        // hidden from the block.
+                        self.patch_jump(rethrow);
+                        self.begin_scope();
+                        self.add_local("", name);
+                        self.finally_handler(f);
+                        self.end_scope();
+                    } else {
+                        self.end_scope();
+                    }
+                } else if let Some(ref f) = *finally {
+                    self.finally_handler(f);
+                }
+
+                for jump in exits {
+                    self.patch_jump(jump);
+                }
+            }
+            Stmt::Var(ref name, ref init) => {
+                match init {
+                    Some(i) => self.expression(i),
+                    None => {
+                        self.emit(OpCode::Nil);
+                    }
+                }
+
+                self.declare_variable(name);
+                self.define_variable(name);
+            }
+            Stmt::While(ref condition, ref body, ref increment) => {
+                let loop_start: usize = self.frame().chunk.len();
+                self.expression(condition);
+
+                let exit_jump: usize = self.emit(OpCode::JumpIfFalse(0));
+                self.emit(OpCode::Pop);
+
+                let scope_depth: usize = self.frame().scope_depth;
+                let tries: usize = self.frame().tries.len();
+                self.frame().loops.push(Loop {
+                    scope_depth,
+                    tries,
+                    breaks: Vec::new(),
+                    continues: Vec::new(),
+                });
+                self.statement(body);
+                let l: Loop = self.frame().loops.pop().unwrap();
+
+                for jump in l.continues {
+                    self.patch_jump(jump);
+                }
+                if let Some(inc) = increment {
+                    self.expression(inc);
+                    self.emit(OpCode::Pop);
+                }
+                self.emit_jump_to(loop_start);
+
+                self.patch_jump(exit_jump);
+                self.emit(OpCode::Pop);
+
+                for jump in l.breaks {
+                    self.patch_jump(jump);
+                }
+            }
+        })
+    }
+
+    // Runs a `finally` block for an error that is still on its way out. The
+    // VM holds the error against the slot it pushes on top of the stack, and
+    // `Rethrow` raises it again once the block has run.
+    fn finally_handler(&mut self, finally: &[Stmt]) {
+        self.begin_scope();
+        self.add_local("", &self.token.clone());
+        self.block(finally);
+        self.emit(OpCode::Rethrow);
+        self.end_scope();
+    }
+
+    fn function(&mut self, name: &Token, params: &[Token], body: &[Stmt], ftype: FunctionType) {
+        self.frames
+            .push(Frame::new(name.lexeme().to_string(), ftype));
+        self.frame().arity = params.len();
+
+        self.begin_scope();
+        for p in params {
+            self.add_local(p.lexeme(), p);
+        }
+        for s in body {
+            self.statement(s);
+        }
+        self.emit_return();
+
+        let proto: Proto = self.end_frame();
+        let idx: usize = self.frame().chunk.add_proto(Rc::new(proto));
+        let idx: u16 = self.check_index(idx, name, "Too many functions in one chunk");
+
+        self.emit(OpCode::Closure(idx));
+    }
+
+    fn expression(&mut self, e: &Expr) {
+        stack::guard(|| match *e {
+            Expr::Assign(ref name, ref value, _) => {
+                self.expression(value);
+                self.named_variable(name, true);
+            }
+            Expr::Binary(ref lhs, ref token, ref rhs) => {
+                self.expression(lhs);
+                self.expression(rhs);
+
+                let op: OpCode = match token.ttype() {
+                    TokenType::BangEqual => OpCode::NotEqual,
+                    TokenType::EqualEqual => OpCode::Equal,
+                    TokenType::Greater => OpCode::Greater,
+                    TokenType::GreaterEqual => OpCode::GreaterEqual,
+                    TokenType::Less => OpCode::Less,
+                    TokenType::LessEqual => OpCode::LessEqual,
+                    TokenType::Plus => OpCode::Add,
+                    TokenType::Minus => OpCode::Subtract,
+                    TokenType::Star => OpCode::Multiply,
+                    TokenType::Slash => OpCode::Divide,
+                    TokenType::Percent => OpCode::Modulo,
+                    TokenType::TildeSlash => OpCode::FloorDivide,
+                    TokenType::StarStar => OpCode::Power,
+                    _ => unreachable!(),
+                };
+                self.emit_at(op, token);
+            }
+            Expr::Call(ref callee, ref paren, ref args) => {
+                self.expression(callee);
+                for a in args {
+                    self.expression(a);
+                }
+
+                self.emit_at(OpCode::Call(args.len() as u8), paren);
+            }
+            Expr::Compound(ref target, ref op, ref value) => self.update(target, op, Some(value)),
+            Expr::Conditional(ref cond, ref then_e, ref else_e) => {
+                self.expression(cond);
+
+                let then_jump: usize = self.emit(OpCode::JumpIfFalse(0));
+                self.emit(OpCode::Pop);
+                self.expression(then_e);
+
+                let else_jump: usize = self.emit(OpCode::Jump(0));
+                self.patch_jump(then_jump);
+                self.emit(OpCode::Pop);
+                self.expression(else_e);
+                self.patch_jump(else_jump);
+            }
+            Expr::Get(ref object, ref name) => {
+                self.expression(object);
+
+                let name_const: u16 = self.identifier_constant(name);
+                self.emit_at(OpCode::GetProperty(name_const), name);
+            }
+            Expr::Grouping(ref expr) => self.expression(expr),
+            Expr::Index(ref object, ref bracket, ref index) => {
+                self.expression(object);
+                self.expression(index);
+
+                self.emit_at(OpCode::GetIndex, bracket);
+            }
+            Expr::List(ref bracket, ref elements) => {
+                for e in elements {
+                    self.expression(e);
+                }
+
+                let count: u16 =
+                    self.check_index(elements.len(), bracket, "Too many elements in list literal");
+                self.emit(OpCode::List(count));
+            }
+            Expr::Literal(_, ref l) => {
+                let op: OpCode = match *l {
+                    Value::Nil => OpCode::Nil,
+                    Value::Bool(true) => OpCode::True,
+                    Value::Bool(false) => OpCode::False,
+                    _ => {
+                        let idx: usize = self.frame().chunk.add_constant(l.clone());
+                        let token: Token = self.token.clone();
+
+                        OpCode::Constant(self.check_index(
+                            idx,
+                            &token,
+                            "Too many constants in one chunk",
+                        ))
+                    }
+                };
+                self.emit(op);
+            }
+            Expr::Logical(ref lhs, ref token, ref rhs) => {
+                self.expression(lhs);
+
+                if token.ttype() == TokenType::Or {
+                    let else_jump: usize = self.emit(OpCode::JumpIfFalse(0));
+                    let end_jump: usize = self.emit(OpCode::Jump(0));
+
+                    self.patch_jump(else_jump);
+                    self.emit(OpCode::Pop);
+                    self.expression(rhs);
+                    self.patch_jump(end_jump);
+                } else {
+                    let end_jump: usize = self.emit(OpCode::JumpIfFalse(0));
+
+                    self.emit(OpCode::Pop);
+                    self.expression(rhs);
+                    self.patch_jump(end_jump);
+                }
+            }
+            Expr::Postfix(ref target, ref op) => self.update(target, op, None),
+            Expr::Set(ref object, ref name, ref value) => {
+                self.expression(object);
+                self.expression(value);
+
+                let name_const: u16 = self.identifier_constant(name);
+                self.emit_at(OpCode::SetProperty(name_const), name);
+            }
+            Expr::Map(ref brace, ref entries) => {
+                for (k, v) in entries {
+                    self.expression(k);
+                    self.expression(v);
+                }
+
+                let count: u16 =
+                    self.check_index(entries.len(), brace, "Too many entries in map literal");
+                self.emit_at(OpCode::Map(count), brace);
+            }
+            Expr::SetIndex(ref object, ref bracket, ref index, ref value) => {
+                self.expression(object);
+                self.expression(index);
+                self.expression(value);
+
+                self.emit_at(OpCode::SetIndex, bracket);
+            }
+            Expr::Stringify(ref expr) => {
+                self.expression(expr);
+                self.emit(OpCode::Stringify);
+            }
+            Expr::Super(ref keyword, ref method, _) => {
+                let this: Token = keyword.synthetic(TokenType::This, "this");
+                self.named_variable(&this, false);
+                self.named_variable(keyword, false);
+
+                let name_const: u16 = self.identifier_constant(method);
+                self.emit_at(OpCode::GetSuper(name_const), method);
+            }
+            Expr::This(ref keyword, _) => self.named_variable(keyword, false),
+            Expr::Unary(ref token, ref expr) => {
+                self.expression(expr);
+
+                match token.ttype() {
+                    TokenType::Minus => self.emit_at(OpCode::Negate, token),
+                    _ => self.emit(OpCode::Not),
+                };
+            }
+            Expr::Var(ref name, _) => self.named_variable(name, false),
+        })
+    }
+
+    // Leaves the target's operands on the stack followed by its current value,
+    // so they are evaluated only once. A postfix update buries a copy of the
+    // old value beneath the operands, where it survives the store.
+    fn update(&mut self, target: &Expr, op: &Token, value: Option<&Rc<Expr>>) {
+        let operands: u8 = match *target {
+            Expr::Var(ref name, _) => {
+                self.named_variable(name, false);
+                0
+            }
+            Expr::Get(ref object, ref name) => {
+                self.expression(object);
+                self.emit(OpCode::Dup(1));
+
+                let name_const: u16 = self.identifier_constant(name);
+                self.emit_at(OpCode::GetProperty(name_const), name);
+                1
+            }
+            Expr::Index(ref object, ref bracket, ref index) => {
+                self.expression(object);
+                self.expression(index);
+                self.emit(OpCode::Dup(2));
+                self.emit_at(OpCode::GetIndex, bracket);
+                2
+            }
+            _ => unreachable!(),
+        };
+
+        match value {
+            Some(v) => self.expression(v),
+            None => {
+                self.emit(OpCode::Dup(1));
+                if operands > 0 {
+                    self.emit(OpCode::Bury(operands + 1));
+                }
+                self.expression(&Expr::Literal(op.clone(), Value::Number(1.0)));
+            }
+        }
+
+        let arith: OpCode = match op.ttype() {
+            TokenType::PlusEqual | TokenType::PlusPlus => OpCode::Add,
+            TokenType::MinusEqual | TokenType::MinusMinus => OpCode::Subtract,
+            TokenType::StarEqual => OpCode::Multiply,
+            TokenType::SlashEqual => OpCode::Divide,
+            _ => unreachable!(),
+        };
+        self.emit_at(arith, op);
+
+        match *target {
+            Expr::Var(ref name, _) => self.named_variable(name, true),
+            Expr::Get(_, ref name) => {
+                let name_const: u16 = self.identifier_constant(name);
+                self.emit_at(OpCode::SetProperty(name_const), name);
+            }
+            Expr::Index(_, ref bracket, _) => {
+                self.emit_at(OpCode::SetIndex, bracket);
+            }
+            _ => unreachable!(),
+        }
+
+        if value.is_none() {
+            self.emit(OpCode::Pop);
+        }
+    }
+
+    fn named_variable(&mut self, name: &Token, assign: bool) {
+        let current: usize = self.frames.len() - 1;
+
+        let op: OpCode = if let Some(slot) = self.resolve_local(current, name.lexeme()) {
+            if assign {
+                OpCode::SetLocal(slot)
+            } else {
+                OpCode::GetLocal(slot)
+            }
+        } else if let Some(idx) = self.resolve_upvalue(current, name) {
+            if assign {
+                OpCode::SetUpvalue(idx)
+            } else {
+                OpCode::GetUpvalue(idx)
+            }
+        } else {
+            let name_const: u16 = self.identifier_constant(name);
+
+            if assign {
+                OpCode::SetGlobal(name_const)
+            } else {
+                OpCode::GetGlobal(name_const)
+            }
+        };
+
+        self.emit_at(op, name);
+    }
+
+    fn resolve_local(&self, frame: usize, name: &str) -> Option<u8> {
+        self.frames[frame]
+            .locals
+            .iter()
+            .rposition(|l| l.name == name)
+            .map(|slot| slot as u8)
+    }
+
+    fn resolve_upvalue(&mut self, frame: usize, name: &Token) -> Option<u8> {
+        if frame == 0 {
+            return None;
+        }
+
+        if let Some(slot) = self.resolve_local(frame - 1, name.lexeme()) {
+            self.frames[frame - 1].locals[slot as usize].is_captured = true;
+
+            return Some(self.add_upvalue(frame, true, slot, name));
+        }
+
+        let idx: u8 = self.resolve_upvalue(frame - 1, name)?;
+        Some(self.add_upvalue(frame, false, idx, name))
+    }
+
+    fn add_upvalue(&mut self, frame: usize, is_local: bool, index: u8, name: &Token) -> u8 {
+        let upvalues: &Vec<(bool, u8)> = &self.frames[frame].upvalues;
+
+        if let Some(i) = upvalues.iter().position(|u| *u == (is_local, index)) {
+            return i as u8;
+        }
+
+        if upvalues.len() > u8::MAX as usize {
+            self.error("Too many closure variables in function", name);
+            return 0;
+        }
+
+        self.frames[frame].upvalues.push((is_local, index));
+        (self.frames[frame].upvalues.len() - 1) as u8
+    }
+
+    fn add_local(&mut self, name: &str, token: &Token) {
+        if self.frame().locals.len() > u8::MAX as usize {
+            self.error("Too many local variables in function", token);
+            return;
+        }
+
+        let depth: usize = self.frame().scope_depth;
+        self.frame().locals.push(Local {
+            name: name.to_string(),
+            depth,
+            is_captured: false,
+        });
+    }
+
+    fn declare_variable(&mut self, name: &Token) {
+        if self.frame().scope_depth > 0 {
+            self.add_local(name.lexeme(), name);
+        }
+    }
+
+    fn define_variable(&mut self, name: &Token) {
+        if self.frame().scope_depth == 0 {
+            let name_const: u16 = self.identifier_constant(name);
+
+            self.emit(OpCode::DefineGlobal(name_const));
+        }
+    }
+
+    fn identifier_constant(&mut self, name: &Token) -> u16 {
+        if let Some(idx) = self.frame().identifiers.get(name.lexeme()) {
+            return *idx;
+        }
+
+        let value: Value = Value::Str(Rc::from(name.lexeme()));
+        let idx: usize = self.frame().chunk.add_constant(value);
+        let idx: u16 = self.check_index(idx, name, "Too many constants in one chunk");
+
+        self.frame()
+            .identifiers
+            .insert(name.lexeme().to_string(), idx);
+        idx
+    }
+
+    fn check_index(&mut self, idx: usize, token: &Token, msg: &'static str) -> u16 {
+        if idx > u16::MAX as usize {
+            if !self.frame().full.contains(&msg) {
+                self.frame().full.push(msg);
+                self.error(msg, token);
+            }
+            return 0;
+        }
+
+        idx as u16
+    }
+
+    fn begin_scope(&mut self) {
+        self.frame().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.frame().scope_depth -= 1;
+
+        loop {
+            let frame: &mut Frame = self.frame();
+            let captured: bool = match frame.locals.last() {
+                Some(l) if l.depth > frame.scope_depth => l.is_captured,
+                _ => break,
+            };
+            frame.locals.pop();
+
+            if captured {
+                self.emit(OpCode::CloseUpvalue);
+            } else {
+                self.emit(OpCode::Pop);
+            }
+        }
+    }
+
+    fn block(&mut self, statements: &[Stmt]) {
+        self.begin_scope();
+        for s in statements {
+            self.statement(s);
+        }
+        self.end_scope();
+    }
+
+    fn exit_loop_tries(&mut self) {
+        if let Some(tries) = self.frame().loops.last().map(|l| l.tries) {
+            self.exit_tries(tries);
+        }
+    }
+
+    // Pops the handlers of the `try` statements being jumped out of and runs
+    // their `finally` blocks, innermost first. Locals declared inside a `try`
+    // are hidden from its `finally` block while it is compiled.
+    fn exit_tries(&mut self, outer: usize) {
+        let tries: Vec<Try> = self.frame().tries.clone();
+        let names: Vec<String> = self.frame().locals.iter().map(|l| l.name.clone()).collect();
+
+        for t in tries[outer..].iter().rev() {
+            self.frame().tries.pop();
+            self.emit(OpCode::PopHandler);
+
+            if let Some(ref f) = t.finally {
+                for l in self.frame().locals.iter_mut() {
+                    if l.depth > t.scope_depth {
+                        l.name = String::new();
+                    }
+                }
+                self.block(f);
+            }
+        }
+
+        self.frame().tries = tries;
+        for (l, name) in self.frame().locals.iter_mut().zip(names) {
+            l.name = name;
+        }
+    }
+
+    // Pops the locals declared inside the innermost loop without forgetting
+    // them, since the code after a `break` or `continue` still sees them.
+    fn discard_loop_locals(&mut self) {
+        let depth: usize = match self.frame().loops.last() {
+            Some(l) => l.scope_depth,
+            None => return,
+        };
+
+        let captured: Vec<bool> = self
+            .frame()
+            .locals
+            .iter()
+            .rev()
+            .take_while(|l| l.depth > depth)
+            .map(|l| l.is_captured)
+            .collect();
+        for c in captured {
+            if c {
+                self.emit(OpCode::CloseUpvalue);
+            } else {
+                self.emit(OpCode::Pop);
+            }
+        }
+    }
+
+    fn emit(&mut self, op: OpCode) -> usize {
+        self.frame().chunk.write(op)
+    }
+
+    fn emit_at(&mut self, op: OpCode, token: &Token) -> usize {
+        self.token = token.clone();
+
+        self.frame().chunk.write_at(op, token)
+    }
+
+    fn emit_return(&mut self) {
+        if self.frame().ftype == FunctionType::Initializer {
+            self.emit(OpCode::GetLocal(0));
+        } else {
+            self.emit(OpCode::Nil);
+        }
+
+        self.emit(OpCode::Return);
+    }
+
+    fn emit_jump_to(&mut self, target: usize) {
+        let target: u32 = jump_target(target);
+
+        self.emit(OpCode::Jump(target));
+    }
+
+    fn patch_jump(&mut self, offset: usize) {
+        let target: u32 = jump_target(self.frame().chunk.len());
+
+        let op: OpCode = match self.frame().chunk.code(offset) {
+            OpCode::Jump(_) => OpCode::Jump(target),
+            OpCode::JumpIfFalse(_) => OpCode::JumpIfFalse(target),
+            OpCode::PushCatch(_) => OpCode::PushCatch(target),
+            OpCode::PushFinally(_) => OpCode::PushFinally(target),
+            _ => unreachable!(),
+        };
+        self.frame().chunk.patch(offset, op);
+    }
+
+    fn error(&mut self, msg: &str, token: &Token) {
+        self.errors
+            .push(CompileError::new(msg.to_string(), token.clone()));
+    }
+}
+
+// Jump targets are absolute instruction positions. A chunk runs out of memory
+// long before it holds 2^32 instructions, so they always fit.
+fn jump_target(target: usize) -> u32 {
+    target as u32
+}
@@ -1,39 +1,44 @@
-use crate::literal::Literal;
-use crate::token::Token;
+use crate::value::Value;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
-#[derive(Clone)]
 pub struct Environment {
-    values: HashMap<String, Option<Rc<dyn Literal>>>,
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
 impl Environment {
     pub fn new() -> Environment {
         Environment {
             values: HashMap::new(),
+            enclosing: None,
         }
     }
 
-    pub fn define(&mut self, name: String, value: Option<Rc<dyn Literal>>) {
-        self.values.insert(name, value);
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Environment {
+        Environment {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
     }
 
-    pub fn get(&self, name: Token) -> Option<&Option<Rc<dyn Literal>>> {
-        if self.values.contains_key(name.lexeme()) {
-            return Some(self.values.get(name.lexeme()).unwrap());
-        }
+    pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
 
-        None
+    pub fn define(&mut self, name: String, value: Value) {
+        self.values.insert(name, value);
     }
 
-    pub fn assign(&mut self, name: Token, value: Option<Rc<dyn Literal>>) -> Option<()> {
-        if self.values.contains_key(name.lexeme()) {
-            self.values.insert(name.lexeme().to_string(), value);
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned()
+    }
 
-            return Some(());
-        }
+    pub fn assign(&mut self, name: &str, value: Value) -> Option<()> {
+        let slot: &mut Value = self.values.get_mut(name)?;
+        *slot = value;
 
-        None
+        Some(())
     }
 }
@@ -1,66 +1,437 @@
-use crate::token::{Token, TokenType};
+use crate::token::{Source, Span, Token, TokenType};
+use crate::value::Value;
+use std::error;
 use std::fmt;
+use std::sync::Arc;
+
+// Static errors stop the source from running at all, while a runtime error
+// stops it at the statement that raised it.
+#[derive(Debug, Clone)]
+pub enum Error {
+    Scan(Vec<ScanError>),
+    Parse(Vec<ParseError>),
+    Resolve(Vec<ResolveError>),
+    Compile(Vec<CompileError>),
+    Runtime(RuntimeError),
+}
+
+impl Error {
+    // Renders every error with its source excerpt, in ANSI colour if asked to.
+    pub fn render(&self, colour: bool) -> String {
+        let errors: Vec<String> = match *self {
+            Error::Scan(ref errors) => errors.iter().map(|e| e.render(colour)).collect(),
+            Error::Parse(ref errors) => errors.iter().map(|e| e.render(colour)).collect(),
+            Error::Resolve(ref errors) => errors.iter().map(|e| e.render(colour)).collect(),
+            Error::Compile(ref errors) => errors.iter().map(|e| e.render(colour)).collect(),
+            Error::Runtime(ref e) => vec![e.render(colour)],
+        };
+
+        errors.join("\n")
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
+impl error::Error for Error {}
+
+// An import that failed on the module's static errors fails the run with
+// those errors rather than as a runtime error.
+impl From<Exception> for Error {
+    fn from(e: Exception) -> Error {
+        match e.cause {
+            Some(cause) => *cause,
+            None => Error::Runtime(RuntimeError {
+                msg: e.msg.into(),
+                at: Location::from(&e.token),
+            }),
+        }
+    }
+}
+
+// Scan errors point at a column rather than a token, since the characters
+// there didn't make one.
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    msg: String,
+    line: usize,
+    column: usize,
+    span: Span,
+    src: Arc<Source>,
+}
+
+impl ScanError {
+    pub fn new(msg: String, line: usize, column: usize, span: Span, src: Arc<Source>) -> ScanError {
+        ScanError {
+            msg,
+            line,
+            column,
+            span,
+            src,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.src.name.as_deref()
+    }
+
+    pub fn render(&self, colour: bool) -> String {
+        let loc: String = format!(" at column {}", self.column);
+        let file: Option<&str> = self.src.name.as_deref();
+        let header: String = paint(
+            &report(file, self.line, loc, self.msg.to_string()),
+            BOLD,
+            colour,
+        );
+
+        format!("{}\n{}", header, excerpt(&self.src.text, self.span, colour))
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
+impl error::Error for ScanError {}
 
 #[derive(Debug, Clone)]
 pub struct ParseError {
     msg: String,
-    token: Token,
+    at: Location,
 }
 
 impl ParseError {
     pub fn new(msg: String, token: Token) -> ParseError {
-        ParseError { msg, token }
+        ParseError {
+            msg,
+            at: Location::from(&token),
+        }
+    }
+
+    // Underlines `span` rather than just the token the error is reported at.
+    pub fn with_span(mut self, span: Span) -> ParseError {
+        self.at.span = span;
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn line(&self) -> usize {
+        self.at.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.at.column
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.at.src.name.as_deref()
+    }
+
+    pub fn render(&self, colour: bool) -> String {
+        self.at.describe(&self.msg, colour)
     }
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let f_str: String = if self.token.ttype() == TokenType::Eof {
-            " at end".to_string()
-        } else {
-            format!(" at '{}'", self.token.lexeme())
-        };
+        write!(f, "{}", self.render(false))
+    }
+}
+
+impl error::Error for ParseError {}
+
+#[derive(Debug, Clone)]
+pub struct ResolveError {
+    msg: String,
+    at: Location,
+}
+
+impl ResolveError {
+    pub fn new(msg: String, token: Token) -> ResolveError {
+        ResolveError {
+            msg,
+            at: Location::from(&token),
+        }
+    }
+
+    // Underlines `span` rather than just the token the error is reported at.
+    pub fn with_span(mut self, span: Span) -> ResolveError {
+        self.at.span = span;
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn line(&self) -> usize {
+        self.at.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.at.column
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.at.src.name.as_deref()
+    }
 
-        write!(
-            f,
-            "{}",
-            report(self.token.line(), f_str, self.msg.to_string())
-        )
+    pub fn render(&self, colour: bool) -> String {
+        self.at.describe(&self.msg, colour)
     }
 }
 
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
+impl error::Error for ResolveError {}
+
+#[derive(Debug, Clone)]
+pub struct CompileError {
+    msg: String,
+    at: Location,
+}
+
+impl CompileError {
+    pub fn new(msg: String, token: Token) -> CompileError {
+        CompileError {
+            msg,
+            at: Location::from(&token),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn line(&self) -> usize {
+        self.at.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.at.column
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.at.src.name.as_deref()
+    }
+
+    pub fn render(&self, colour: bool) -> String {
+        self.at.describe(&self.msg, colour)
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(false))
+    }
+}
+
+impl error::Error for CompileError {}
+
+// What a run failed with, as the host sees it.
 #[derive(Debug, Clone)]
 pub struct RuntimeError {
     msg: String,
-    token: Token,
+    at: Location,
 }
 
 impl RuntimeError {
-    pub fn new(msg: String, token: Token) -> RuntimeError {
-        RuntimeError { msg, token }
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn line(&self) -> usize {
+        self.at.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.at.column
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.at.src.name.as_deref()
+    }
+
+    pub fn render(&self, colour: bool) -> String {
+        self.at.describe(&self.msg, colour)
     }
 }
 
 impl fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let f_str: String = if self.token.ttype() == TokenType::Eof {
-            " at end".to_string()
-        } else {
-            format!(" at '{}'", self.token.lexeme())
+        write!(f, "{}", self.render(false))
+    }
+}
+
+impl error::Error for RuntimeError {}
+
+// A runtime error on its way up through the script, where `catch` can stop
+// it. It carries the value it was thrown with, which a host never sees.
+#[derive(Debug, Clone)]
+pub struct Exception {
+    msg: Box<str>,
+    token: Token,
+    value: Option<Value>,
+    cause: Option<Box<Error>>,
+}
+
+impl Exception {
+    pub fn new(msg: String, token: Token) -> Exception {
+        Exception {
+            msg: msg.into_boxed_str(),
+            token,
+            value: None,
+            cause: None,
+        }
+    }
+
+    pub fn thrown(msg: String, token: Token, value: Value) -> Exception {
+        Exception {
+            msg: msg.into_boxed_str(),
+            token,
+            value: Some(value),
+            cause: None,
+        }
+    }
+
+    pub fn with_cause(self, cause: Error) -> Exception {
+        Exception {
+            cause: Some(Box::new(cause)),
+            ..self
+        }
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn value(&self) -> Option<&Value> {
+        self.value.as_ref()
+    }
+}
+
+// Where an error was reported, copied out of the token so that errors don't
+// hold on to script values and can be sent to other threads.
+#[derive(Debug, Clone)]
+struct Location {
+    lexeme: Option<Box<str>>,
+    line: usize,
+    column: usize,
+    span: Span,
+    src: Arc<Source>,
+}
+
+impl From<&Token> for Location {
+    fn from(token: &Token) -> Location {
+        Location {
+            lexeme: (token.ttype() != TokenType::Eof).then(|| Box::from(token.lexeme())),
+            line: token.line(),
+            column: token.column(),
+            span: token.span(),
+            src: token.source(),
+        }
+    }
+}
+
+impl Location {
+    fn describe(&self, msg: &str, colour: bool) -> String {
+        let loc: String = match self.lexeme {
+            Some(ref lexeme) => format!(" at '{}'", lexeme),
+            None => " at end".to_string(),
         };
+        let file: Option<&str> = self.src.name.as_deref();
+        let header: String = paint(&report(file, self.line, loc, msg.to_string()), BOLD, colour);
 
-        write!(
-            f,
-            "{}",
-            report(self.token.line(), f_str, self.msg.to_string())
-        )
+        if self.src.text.is_empty() {
+            return header;
+        }
+
+        format!("{}\n{}", header, excerpt(&self.src.text, self.span, colour))
     }
 }
 
-pub fn report(line: usize, loc: String, message: String) -> String {
-    format!("[line {}] Error{}: {}", line, loc, message)
+pub fn report(file: Option<&str>, line: usize, loc: String, message: String) -> String {
+    match file {
+        Some(file) => format!("[{}:{}] Error{}: {}", file, line, loc, message),
+        None => format!("[line {}] Error{}: {}", line, loc, message),
+    }
 }
 
-pub fn error(line: usize, message: String) {
-    report(line, "".to_string(), message);
+// Shows the source line `span` starts on, with the part of it the span covers
+// underlined. Spans running past the end of the line are cut short there. The
+// line is numbered from the span, since it can start before the token that
+// gives the error its line.
+fn excerpt(src: &str, span: Span, colour: bool) -> String {
+    let (start, end): (usize, usize) = (span.range().start, span.range().end);
+    let line: usize = src[..start].matches('\n').count() + 1;
+    let line_start: usize = src[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end: usize = src[start..].find('\n').map_or(src.len(), |i| start + i);
+    let text: &str = src[line_start..line_end].trim_end_matches('\r');
+
+    // Tabs are kept so the carets line up however wide the terminal draws them.
+    let pad: String = src[line_start..start]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width: usize = src[start..end.clamp(start, line_end)]
+        .chars()
+        .count()
+        .max(1);
+
+    let gutter: String = " ".repeat(line.to_string().len());
+    let bar: String = paint("|", BLUE, colour);
+    let carets: String = paint(&"^".repeat(width), RED, colour);
+
+    format!(
+        "{} {}\n{} {} {}\n{} {} {}{}",
+        gutter,
+        bar,
+        paint(&line.to_string(), BLUE, colour),
+        bar,
+        text,
+        gutter,
+        bar,
+        pad,
+        carets
+    )
+}
+
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+const RED: &str = "\x1b[1;31m";
+
+fn paint(text: &str, style: &str, colour: bool) -> String {
+    if colour {
+        format!("{}{}\x1b[0m", style, text)
+    } else {
+        text.to_string()
+    }
 }
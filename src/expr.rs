@@ -1,13 +1,134 @@
-use crate::literal::Literal;
-use crate::token::Token;
+use crate::token::{Span, Token, TokenType};
+use crate::value::Value;
+use std::cell::Cell;
+use std::mem;
 use std::rc::Rc;
 
+// Variable-like nodes carry the scope depth found by the resolver; `None`
+// means the name lives in the global environment.
 pub enum Expr {
-    Assign(Token, Rc<Expr>),
+    Assign(Token, Rc<Expr>, Cell<Option<usize>>),
     Binary(Rc<Expr>, Token, Rc<Expr>),
+    Call(Rc<Expr>, Token, Vec<Expr>),
+    Compound(Rc<Expr>, Token, Rc<Expr>),
+    Conditional(Rc<Expr>, Rc<Expr>, Rc<Expr>),
+    Get(Rc<Expr>, Token),
     Grouping(Rc<Expr>),
-    Literal(Option<Rc<dyn Literal>>),
+    Index(Rc<Expr>, Token, Rc<Expr>),
+    List(Token, Vec<Expr>),
+    Literal(Token, Value),
     Logical(Rc<Expr>, Token, Rc<Expr>),
+    Map(Token, Vec<(Expr, Expr)>),
+    Postfix(Rc<Expr>, Token),
+    Set(Rc<Expr>, Token, Rc<Expr>),
+    SetIndex(Rc<Expr>, Token, Rc<Expr>, Rc<Expr>),
+    Stringify(Rc<Expr>),
+    Super(Token, Token, Cell<Option<usize>>),
+    This(Token, Cell<Option<usize>>),
     Unary(Token, Rc<Expr>),
-    Var(Token),
+    Var(Token, Cell<Option<usize>>),
+}
+
+impl Expr {
+    // Nodes don't store a span of their own, it is worked out from the tokens
+    // they are made of. Brackets that aren't kept as tokens are left out.
+    pub fn span(&self) -> Span {
+        match *self {
+            Expr::Assign(ref name, ref value, _) => name.span().to(value.span()),
+            Expr::Binary(ref l, _, ref r) | Expr::Logical(ref l, _, ref r) => l.span().to(r.span()),
+            Expr::Call(ref callee, ref paren, _) => callee.span().to(paren.span()),
+            Expr::Compound(ref target, ref op, ref value) => {
+                target.span().to(op.span()).to(value.span())
+            }
+            Expr::Conditional(ref cond, _, ref otherwise) => cond.span().to(otherwise.span()),
+            Expr::Get(ref object, ref name) => object.span().to(name.span()),
+            Expr::Grouping(ref e) | Expr::Stringify(ref e) => e.span(),
+            Expr::Index(ref object, ref bracket, _) => object.span().to(bracket.span()),
+            Expr::List(ref bracket, ref elements) => elements
+                .iter()
+                .fold(bracket.span(), |span, e| span.to(e.span())),
+            Expr::Literal(ref token, _) | Expr::This(ref token, _) | Expr::Var(ref token, _) => {
+                token.span()
+            }
+            Expr::Map(ref brace, ref entries) => entries
+                .iter()
+                .fold(brace.span(), |span, (k, v)| span.to(k.span()).to(v.span())),
+            Expr::Postfix(ref target, ref op) => target.span().to(op.span()),
+            Expr::Set(ref object, _, ref value) => object.span().to(value.span()),
+            Expr::SetIndex(ref object, _, _, ref value) => object.span().to(value.span()),
+            Expr::Super(ref keyword, ref method, _) => keyword.span().to(method.span()),
+            Expr::Unary(ref op, ref r) => op.span().to(r.span()),
+        }
+    }
+}
+
+thread_local! {
+    // Stands in for the children a node gives up when it is freed.
+    static EMPTY: Rc<Expr> = Rc::new(Expr::Literal(
+        Token::new(TokenType::Nil, String::new(), None, 0),
+        Value::Nil,
+    ));
+}
+
+// A chain of binary operators nests as deep as it is long, so freeing a node
+// moves its children onto a worklist rather than recursing into them.
+impl Drop for Expr {
+    fn drop(&mut self) {
+        let mut work: Vec<Expr> = Vec::new();
+        self.take_children(&mut work);
+        while let Some(mut e) = work.pop() {
+            e.take_children(&mut work);
+        }
+    }
+}
+
+impl Expr {
+    fn take_children(&mut self, work: &mut Vec<Expr>) {
+        match *self {
+            Expr::Assign(_, ref mut e, _)
+            | Expr::Get(ref mut e, _)
+            | Expr::Grouping(ref mut e)
+            | Expr::Postfix(ref mut e, _)
+            | Expr::Stringify(ref mut e)
+            | Expr::Unary(_, ref mut e) => take(e, work),
+            Expr::Binary(ref mut l, _, ref mut r)
+            | Expr::Compound(ref mut l, _, ref mut r)
+            | Expr::Index(ref mut l, _, ref mut r)
+            | Expr::Logical(ref mut l, _, ref mut r)
+            | Expr::Set(ref mut l, _, ref mut r) => {
+                take(l, work);
+                take(r, work);
+            }
+            Expr::Conditional(ref mut a, ref mut b, ref mut c)
+            | Expr::SetIndex(ref mut a, _, ref mut b, ref mut c) => {
+                take(a, work);
+                take(b, work);
+                take(c, work);
+            }
+            Expr::Call(ref mut callee, _, ref mut args) => {
+                take(callee, work);
+                work.append(args);
+            }
+            Expr::List(_, ref mut elements) => work.append(elements),
+            Expr::Map(_, ref mut entries) => {
+                for (k, v) in entries.drain(..) {
+                    work.push(k);
+                    work.push(v);
+                }
+            }
+            Expr::Literal(..) | Expr::Super(..) | Expr::This(..) | Expr::Var(..) => (),
+        }
+    }
+}
+
+// Children shared with another node are left for that node to free.
+fn take(e: &mut Rc<Expr>, work: &mut Vec<Expr>) {
+    if Rc::get_mut(e).is_none() {
+        return;
+    }
+    if let Ok(empty) = EMPTY.try_with(Rc::clone) {
+        if let Ok(e) = Rc::try_unwrap(mem::replace(e, empty)) {
+            work.push(e);
+        }
+    }
 }
@@ -1,253 +1,688 @@
-use crate::error::RuntimeError;
+use crate::builtins;
+use crate::environment::Environment;
+use crate::error::{Error, Exception};
 use crate::expr::Expr;
-use crate::literal::*;
-use crate::scope::Scope;
+use crate::module::{self, Modules};
+use crate::scope::{Saved, Scope};
+use crate::stack;
 use crate::stmt::Stmt;
 use crate::token::{Token, TokenType};
+use crate::value::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
+enum Unwind {
+    Break,
+    Continue,
+    Return(Value),
+    Error(Exception),
+}
+
+impl From<Exception> for Unwind {
+    fn from(e: Exception) -> Unwind {
+        Unwind::Error(e)
+    }
+}
+
+const DEPTH_MAX: usize = 1024;
+
 pub struct Interpreter {
     scope: Scope,
+    depth: usize,
+    modules: Modules,
+    natives: Vec<Rc<Native>>,
+    out: Box<dyn Write>,
+    diagnostics: Box<dyn Write>,
 }
 
 impl Interpreter {
     pub fn new() -> Interpreter {
-        Interpreter {
+        let mut interpreter: Interpreter = Interpreter {
             scope: Scope::new(),
+            depth: 0,
+            modules: Modules::new(),
+            natives: Vec::new(),
+            out: Box::new(io::stdout()),
+            diagnostics: Box::new(io::stderr()),
+        };
+        for native in builtins::natives() {
+            interpreter.define_native(native);
         }
+
+        interpreter
     }
 
-    pub fn interpret(&mut self, statements: Vec<Stmt>) {
+    // Modules imported afterwards see the native too.
+    pub fn define_native(&mut self, native: Native) {
+        let native: Rc<Native> = Rc::new(native);
+
+        self.scope
+            .define(native.name.clone(), Value::Native(native.clone()));
+        self.natives.push(native);
+    }
+
+    pub fn set_output(&mut self, out: Box<dyn Write>) {
+        self.out = out;
+    }
+
+    pub fn set_diagnostics(&mut self, diagnostics: Box<dyn Write>) {
+        self.diagnostics = diagnostics;
+    }
+
+    pub fn diagnostics(&mut self) -> &mut dyn Write {
+        &mut *self.diagnostics
+    }
+
+    pub fn set_path(&mut self, path: &Path) {
+        self.modules.set_main(path);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.scope.globals().borrow().get(name)
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.scope
+            .globals()
+            .borrow_mut()
+            .define(name.to_string(), value);
+    }
+
+    pub fn interpret(&mut self, statements: Vec<Stmt>) -> Result<(), Error> {
         for s in statements {
             match self.execute(&s) {
                 Ok(_) => (),
-                Err(e) => println!("{}", e),
+                Err(Unwind::Error(e)) => return Err(Error::from(e)),
+                Err(Unwind::Return(_)) => break,
+                Err(Unwind::Break) | Err(Unwind::Continue) => unreachable!(),
             }
         }
+
+        Ok(())
     }
 
-    fn execute(&mut self, s: &Stmt) -> Result<(), RuntimeError> {
-        match *s {
-            Stmt::Expr(ref e) => {
-                let _ = self.eval(e);
-            }
-            Stmt::If(ref cond, ref then_s, ref else_s) => {
-                if Self::is_truthy(self.eval(cond)?) {
-                    self.execute(then_s)?;
-                } else {
-                    self.execute(else_s.as_ref().unwrap())?;
+    fn execute(&mut self, s: &Stmt) -> Result<(), Unwind> {
+        stack::guard(|| {
+            match *s {
+                Stmt::Break(_) => return Err(Unwind::Break),
+                Stmt::Class(ref name, ref superclass, ref methods) => {
+                    let mut class_methods: HashMap<String, Value> = HashMap::new();
+
+                    let mut super_val: Option<Value> = None;
+                    if let Some(ref expr @ Expr::Var(ref super_name, _)) = *superclass {
+                        match self.eval(expr)? {
+                            Value::Class(class) => {
+                                class_methods.extend(class.methods.borrow().clone());
+                                super_val = Some(Value::Class(class));
+                            }
+                            _ => {
+                                return Err(Unwind::Error(Exception::new(
+                                    "Superclass must be a class".to_string(),
+                                    super_name.clone(),
+                                )))
+                            }
+                        }
+                    }
+
+                    self.scope.define(name.lexeme().to_string(), Value::Nil);
+
+                    if let Some(ref val) = super_val {
+                        self.scope.wrap();
+                        self.scope.define("super".to_string(), val.clone());
+                    }
+
+                    for m in methods {
+                        if let Stmt::Function(ref m_name, ref params, ref body) = *m {
+                            let fun: Function = Function {
+                                name: m_name.clone(),
+                                params: params.clone(),
+                                body: body.clone(),
+                                closure: self.scope.current(),
+                                globals: self.scope.globals(),
+                                is_initializer: m_name.lexeme() == "init",
+                            };
+
+                            class_methods
+                                .insert(m_name.lexeme().to_string(), Value::Function(Rc::new(fun)));
+                        }
+                    }
+
+                    if super_val.is_some() {
+                        self.scope.unwrap();
+                    }
+
+                    let class: Class = Class {
+                        name: name.lexeme().to_string(),
+                        methods: RefCell::new(class_methods),
+                    };
+                    self.scope
+                        .define(name.lexeme().to_string(), Value::Class(Rc::new(class)));
+                }
+                Stmt::Continue(_) => return Err(Unwind::Continue),
+                Stmt::Expr(ref e) => {
+                    self.eval(e)?;
+                }
+                Stmt::Function(ref name, ref params, ref body) => {
+                    let fun: Function = Function {
+                        name: name.clone(),
+                        params: params.clone(),
+                        body: body.clone(),
+                        closure: self.scope.current(),
+                        globals: self.scope.globals(),
+                        is_initializer: false,
+                    };
+
+                    self.scope
+                        .define(name.lexeme().to_string(), Value::Function(Rc::new(fun)))
+                }
+                Stmt::If(ref cond, ref then_s, ref else_s) => {
+                    if self.eval(cond)?.is_truthy() {
+                        self.execute(then_s)?;
+                    } else if let Some(else_s) = else_s {
+                        self.execute(else_s)?;
+                    }
+                }
+                Stmt::Import(ref path, ref name) => {
+                    let module: Value = self.import(path)?;
+
+                    self.scope.define(name.lexeme().to_string(), module)
+                }
+                Stmt::Print(ref e) => {
+                    let value: Value = self.eval(e)?;
+                    let _ = writeln!(self.out, "{}", value);
+                }
+                Stmt::Return(_, ref value) => {
+                    let val: Value = match value {
+                        Some(v) => self.eval(v)?,
+                        None => Value::Nil,
+                    };
+
+                    return Err(Unwind::Return(val));
+                }
+                Stmt::Throw(ref keyword, ref value) => {
+                    let val: Value = self.eval(value)?;
+
+                    return Err(Unwind::Error(builtins::throw(val, keyword.clone())));
+                }
+                Stmt::Try(ref body, ref catch, ref finally) => {
+                    let mut res: Result<(), Unwind> = self.execute_block(body);
+
+                    if let Some((ref name, ref handler)) = *catch {
+                        res = match res {
+                            Err(Unwind::Error(e)) => {
+                                self.scope.wrap();
+                                self.scope
+                                    .define(name.lexeme().to_string(), builtins::catch(&e));
+                                let res: Result<(), Unwind> = self.execute_all(handler);
+                                self.scope.unwrap();
+
+                                res
+                            }
+                            other => other,
+                        };
+                    }
+
+                    if let Some(ref finally) = *finally {
+                        self.execute_block(finally)?;
+                    }
+
+                    res?
+                }
+                Stmt::Var(ref token, ref init) => {
+                    let val: Value = match init {
+                        Some(n) => self.eval(n)?,
+                        None => Value::Nil,
+                    };
+
+                    self.scope.define(token.lexeme().to_string(), val)
+                }
+                Stmt::Block(ref statements) => self.execute_block(statements)?,
+                Stmt::While(ref condition, ref body, ref increment) => {
+                    while self.eval(condition)?.is_truthy() {
+                        match self.execute(body) {
+                            Ok(_) | Err(Unwind::Continue) => (),
+                            Err(Unwind::Break) => break,
+                            Err(e) => return Err(e),
+                        }
+
+                        if let Some(inc) = increment {
+                            self.eval(inc)?;
+                        }
+                    }
                 }
             }
-            Stmt::Print(ref e) => match self.eval(e)? {
-                Some(val) => println!("{}", val),
-                None => println!("nil"),
-            },
-            Stmt::Var(ref token, ref init) => {
-                let val: Option<Rc<dyn Literal>> = match init {
-                    Some(n) => self.eval(n)?,
-                    None => None,
-                };
 
-                self.scope.define(token.lexeme().to_string(), val)
+            Ok(())
+        })
+    }
+
+    fn execute_block(&mut self, statements: &[Stmt]) -> Result<(), Unwind> {
+        self.scope.wrap();
+        let res: Result<(), Unwind> = self.execute_all(statements);
+        self.scope.unwrap();
+
+        res
+    }
+
+    fn execute_all(&mut self, statements: &[Stmt]) -> Result<(), Unwind> {
+        for s in statements {
+            self.execute(s)?;
+        }
+
+        Ok(())
+    }
+
+    fn import(&mut self, path: &Token) -> Result<Value, Exception> {
+        let file: PathBuf = match path.literal() {
+            Some(Value::Str(s)) => self.modules.resolve(s),
+            _ => unreachable!(),
+        };
+        if let Some(module) = self.modules.get(&file) {
+            return Ok(module);
+        }
+
+        let statements: Vec<Stmt> = self.modules.begin(file.clone(), path)?;
+
+        let globals: Rc<RefCell<Environment>> =
+            Rc::new(RefCell::new(builtins::globals(&self.natives)));
+        let saved: Saved = self.scope.enter_call(globals.clone(), globals.clone());
+        let res: Result<(), Unwind> = self.execute_all(&statements);
+        self.scope.exit_call(saved);
+
+        match res {
+            Ok(()) => {
+                let module: Value = Value::Module(Rc::new(Module {
+                    name: module::name(&file),
+                    globals,
+                }));
+                self.modules.finish(Some(module.clone()));
+
+                Ok(module)
+            }
+            Err(Unwind::Error(e)) => {
+                self.modules.finish(None);
+                Err(e)
             }
-            Stmt::Block(ref statements) => {
-                self.scope.wrap();
+            Err(_) => unreachable!(),
+        }
+    }
 
-                for s in statements {
-                    self.execute(s)?;
-                }
+    fn call(&mut self, callee: Value, paren: Token, args: Vec<Value>) -> Result<Value, Exception> {
+        if self.depth >= DEPTH_MAX {
+            return Err(Exception::new("Stack overflow".to_string(), paren));
+        }
+
+        match callee {
+            Value::Function(fun) => {
+                Self::check_arity(fun.params.len(), args.len(), paren)?;
+
+                self.call_function(&fun, args)
+            }
+            Value::Native(native) => {
+                Self::check_arity(native.arity, args.len(), paren.clone())?;
 
-                self.scope.unwrap();
+                (native.fun)(&args).map_err(|msg| Exception::new(msg, paren))
             }
-            Stmt::While(ref condition, ref body) => {
-                while Self::is_truthy(self.eval(condition)?) {
-                    self.execute(body)?;
+            Value::Class(class) => {
+                let init: Option<Value> = class.find_method("init");
+                let arity: usize = match init {
+                    Some(Value::Function(ref init)) => init.params.len(),
+                    _ => 0,
+                };
+                Self::check_arity(arity, args.len(), paren)?;
+
+                let instance: Value = Value::Instance(Rc::new(Instance {
+                    class,
+                    fields: RefCell::new(HashMap::new()),
+                }));
+                if let Some(Value::Function(init)) = init {
+                    self.call_function(&init.bind(instance.clone()), args)?;
                 }
+
+                Ok(instance)
             }
+            _ => Err(Exception::new(
+                "Can only call functions and classes".to_string(),
+                paren,
+            )),
+        }
+    }
+
+    fn call_function(&mut self, fun: &Function, args: Vec<Value>) -> Result<Value, Exception> {
+        let mut env: Environment = Environment::with_enclosing(fun.closure.clone());
+        for (param, arg) in fun.params.iter().zip(args) {
+            env.define(param.lexeme().to_string(), arg);
+        }
+
+        let saved: Saved = self
+            .scope
+            .enter_call(Rc::new(RefCell::new(env)), fun.globals.clone());
+        self.depth += 1;
+        let res: Result<(), Unwind> = self.execute_all(&fun.body);
+        self.depth -= 1;
+        self.scope.exit_call(saved);
+
+        let val: Value = match res {
+            Ok(_) => Value::Nil,
+            Err(Unwind::Return(val)) => val,
+            Err(Unwind::Error(e)) => return Err(e),
+            Err(Unwind::Break) | Err(Unwind::Continue) => unreachable!(),
+        };
+
+        if fun.is_initializer {
+            return Ok(fun.closure.borrow().get("this").unwrap_or(Value::Nil));
+        }
+
+        Ok(val)
+    }
+
+    fn check_arity(arity: usize, got: usize, paren: Token) -> Result<(), Exception> {
+        if arity != got {
+            return Err(Exception::new(
+                format!("Expected {} arguments but got {}", arity, got),
+                paren,
+            ));
         }
 
         Ok(())
     }
 
-    fn eval(&mut self, e: &Expr) -> Result<Option<Rc<dyn Literal>>, RuntimeError> {
-        match *e {
-            Expr::Literal(ref l) => Ok(l.clone()),
+    fn bind(class: &Class, this: Value, name: &Token) -> Result<Value, Exception> {
+        match class.find_method(name.lexeme()) {
+            Some(Value::Function(method)) => Ok(Value::Function(Rc::new(method.bind(this)))),
+            _ => Err(Exception::new(
+                format!("Undefined property '{}'", name.lexeme()),
+                name.clone(),
+            )),
+        }
+    }
+
+    fn eval(&mut self, e: &Expr) -> Result<Value, Exception> {
+        stack::guard(|| match *e {
+            Expr::Literal(_, ref l) => Ok(l.clone()),
             Expr::Logical(ref l, ref t, ref r) => {
-                let left = self.eval(l)?;
+                let left: Value = self.eval(l)?;
 
                 if t.ttype() == TokenType::Or {
-                    if Self::is_truthy(left.clone()) {
+                    if left.is_truthy() {
                         return Ok(left);
                     }
-                } else if !Self::is_truthy(left.clone()) {
+                } else if !left.is_truthy() {
                     return Ok(left);
                 }
 
                 self.eval(r)
             }
-            Expr::Grouping(ref expr) => self.eval(expr),
-            Expr::Var(ref token) => self.scope.get(token.clone()).cloned(),
-            Expr::Assign(ref token, ref expr) => {
-                let val: Option<Rc<dyn Literal>> = self.eval(expr)?;
+            Expr::Call(ref callee, ref paren, ref args) => {
+                let callee: Value = self.eval(callee)?;
 
-                self.scope.assign(token.clone(), val.clone())?;
-                Ok(val)
-            }
-            Expr::Unary(ref token, ref expr) => {
-                let right: Option<Rc<dyn Literal>> = self.eval(expr)?;
-
-                match token.ttype() {
-                    TokenType::Minus => {
-                        let r_clone = Self::unwrap_optional(right, token.clone())?;
-                        let val: f64 = Self::unwrap_number(r_clone, token.clone())?;
+                let mut arguments: Vec<Value> = Vec::new();
+                for a in args {
+                    arguments.push(self.eval(a)?);
+                }
 
-                        Ok(Some(Rc::new(NumberLiteral::new(-val))))
-                    }
-                    TokenType::Bang => Ok(Some(Rc::new(BoolLiteral::new(!Self::is_truthy(right))))),
-                    _ => todo!(),
+                self.call(callee, paren.clone(), arguments)
+            }
+            Expr::Conditional(ref cond, ref then_e, ref else_e) => {
+                if self.eval(cond)?.is_truthy() {
+                    self.eval(then_e)
+                } else {
+                    self.eval(else_e)
                 }
             }
-            Expr::Binary(ref lhs, ref token, ref rhs) => {
-                let left: Option<Rc<dyn Literal>> = self.eval(lhs)?;
-                let right: Option<Rc<dyn Literal>> = self.eval(rhs)?;
-
-                let l_clone = Self::unwrap_optional(left.clone(), token.clone())?;
-                let r_clone = Self::unwrap_optional(right.clone(), token.clone())?;
-
-                match token.ttype() {
-                    TokenType::Minus => {
-                        let l_val: f64 = Self::unwrap_number(l_clone, token.clone())?;
-                        let r_val: f64 = Self::unwrap_number(r_clone, token.clone())?;
+            Expr::Compound(ref target, ref op, ref value) => {
+                Ok(self.update(target, op, Some(value))?.1)
+            }
+            Expr::Get(ref object, ref name) => {
+                let object: Value = self.eval(object)?;
 
-                        Ok(Some(Rc::new(NumberLiteral::new(l_val - r_val))))
-                    }
-                    TokenType::Slash => {
-                        let l_val: f64 = Self::unwrap_number(l_clone, token.clone())?;
-                        let r_val: f64 = Self::unwrap_number(r_clone, token.clone())?;
+                Self::get(object, name)
+            }
+            Expr::Postfix(ref target, ref op) => Ok(self.update(target, op, None)?.0),
+            Expr::Set(ref object, ref name, ref value) => match self.eval(object)? {
+                object @ Value::Instance(_) => {
+                    let val: Value = self.eval(value)?;
+                    Self::set(&object, name, val.clone())?;
 
-                        Ok(Some(Rc::new(NumberLiteral::new(l_val / r_val))))
-                    }
-                    TokenType::Star => {
-                        let l_val: f64 = Self::unwrap_number(l_clone, token.clone())?;
-                        let r_val: f64 = Self::unwrap_number(r_clone, token.clone())?;
+                    Ok(val)
+                }
+                _ => Err(Exception::new(
+                    "Only instances have fields".to_string(),
+                    name.clone(),
+                )),
+            },
+            Expr::SetIndex(ref object, ref bracket, ref index, ref value) => {
+                let object: Value = self.eval(object)?;
+                let index: Value = self.eval(index)?;
+                let val: Value = self.eval(value)?;
 
-                        Ok(Some(Rc::new(NumberLiteral::new(l_val * r_val))))
-                    }
-                    TokenType::Greater => {
-                        let l_val: f64 = Self::unwrap_number(l_clone, token.clone())?;
-                        let r_val: f64 = Self::unwrap_number(r_clone, token.clone())?;
+                builtins::set_index(&object, &index, val.clone())
+                    .map_err(|msg| Exception::new(msg, bracket.clone()))?;
+                Ok(val)
+            }
+            Expr::Stringify(ref expr) => {
+                let val: Value = self.eval(expr)?;
 
-                        Ok(Some(Rc::new(BoolLiteral::new(l_val > r_val))))
-                    }
-                    TokenType::GreaterEqual => {
-                        let l_val: f64 = Self::unwrap_number(l_clone, token.clone())?;
-                        let r_val: f64 = Self::unwrap_number(r_clone, token.clone())?;
+                Ok(Value::Str(Rc::from(val.to_string())))
+            }
+            Expr::Super(ref keyword, ref method, ref depth) => {
+                let distance: usize = depth.get().unwrap();
+                let superclass: Value = self.scope.get(keyword.clone(), Some(distance))?;
+                let this_token: Token = keyword.synthetic(TokenType::This, "this");
+                let this: Value = self.scope.get(this_token, Some(distance - 1))?;
+
+                match superclass {
+                    Value::Class(ref class) => Self::bind(class, this, method),
+                    _ => unreachable!(),
+                }
+            }
+            Expr::This(ref keyword, ref depth) => self.scope.get(keyword.clone(), depth.get()),
+            Expr::Grouping(ref expr) => self.eval(expr),
+            Expr::Index(ref object, ref bracket, ref index) => {
+                let object: Value = self.eval(object)?;
+                let index: Value = self.eval(index)?;
 
-                        Ok(Some(Rc::new(BoolLiteral::new(l_val >= r_val))))
-                    }
-                    TokenType::Less => {
-                        let l_val: f64 = Self::unwrap_number(l_clone, token.clone())?;
-                        let r_val: f64 = Self::unwrap_number(r_clone, token.clone())?;
+                builtins::get_index(&object, &index)
+                    .map_err(|msg| Exception::new(msg, bracket.clone()))
+            }
+            Expr::List(_, ref elements) => {
+                let mut list: Vec<Value> = Vec::new();
+                for e in elements {
+                    list.push(self.eval(e)?);
+                }
 
-                        Ok(Some(Rc::new(BoolLiteral::new(l_val < r_val))))
-                    }
-                    TokenType::LessEqual => {
-                        let l_val: f64 = Self::unwrap_number(l_clone, token.clone())?;
-                        let r_val: f64 = Self::unwrap_number(r_clone, token.clone())?;
+                Ok(Value::List(Rc::new(RefCell::new(List::from(list)))))
+            }
+            Expr::Map(ref brace, ref entries) => {
+                let mut map: Map = Map::new();
+                for (k, v) in entries {
+                    let key: Value = self.eval(k)?;
+                    let val: Value = self.eval(v)?;
+
+                    let key: Key = builtins::map_key(&key)
+                        .map_err(|msg| Exception::new(msg, brace.clone()))?;
+                    map.insert(key, val);
+                }
 
-                        Ok(Some(Rc::new(BoolLiteral::new(l_val <= r_val))))
-                    }
-                    TokenType::Plus => match l_clone.ltype() {
-                        LiteralType::Number => {
-                            let l_val: f64 = Self::unwrap_number(l_clone, token.clone())?;
-                            let r_val: f64 = Self::unwrap_number(r_clone, token.clone())?;
+                Ok(Value::Map(Rc::new(RefCell::new(map))))
+            }
+            Expr::Var(ref token, ref depth) => self.scope.get(token.clone(), depth.get()),
+            Expr::Assign(ref token, ref expr, ref depth) => {
+                let val: Value = self.eval(expr)?;
 
-                            Ok(Some(Rc::new(NumberLiteral::new(l_val + r_val))))
-                        }
-                        LiteralType::String => {
-                            let l_val: String = Self::unwrap_string(l_clone, token.clone())?;
-                            let r_val: String = Self::unwrap_string(r_clone, token.clone())?;
+                self.scope.assign(token.clone(), depth.get(), val.clone())?;
+                Ok(val)
+            }
+            Expr::Unary(ref token, ref expr) => {
+                let right: Value = self.eval(expr)?;
 
-                            Ok(Some(Rc::new(StrLiteral::new(l_val + &r_val))))
-                        }
-                        _ => panic!(),
-                    },
-                    TokenType::EqualEqual => {
-                        Ok(Some(Rc::new(BoolLiteral::new(Self::is_equal(left, right)))))
-                    }
-                    TokenType::BangEqual => Ok(Some(Rc::new(BoolLiteral::new(!Self::is_equal(
-                        left, right,
-                    ))))),
-                    _ => todo!(),
+                match token.ttype() {
+                    TokenType::Minus => Ok(Value::Number(-Self::unwrap_number(&right, token)?)),
+                    TokenType::Bang => Ok(Value::Bool(!right.is_truthy())),
+                    _ => unreachable!(),
                 }
             }
-        }
-    }
-
-    fn is_truthy(l: Option<Rc<dyn Literal>>) -> bool {
-        if l.is_none() {
-            return false;
-        }
+            Expr::Binary(ref lhs, ref token, ref rhs) => {
+                let left: Value = self.eval(lhs)?;
+                let right: Value = self.eval(rhs)?;
 
-        l.unwrap().ltype() != LiteralType::False
+                Self::binary(token.ttype(), left, right, token)
+            }
+        })
     }
 
-    fn is_equal(l: Option<Rc<dyn Literal>>, r: Option<Rc<dyn Literal>>) -> bool {
-        if r.is_none() {
-            return l.is_none();
-        }
-
-        let l = l.unwrap();
-        let r = r.unwrap();
+    fn get(object: Value, name: &Token) -> Result<Value, Exception> {
+        match object {
+            Value::Instance(instance) => {
+                let field: Option<Value> = instance.fields.borrow().get(name.lexeme()).cloned();
 
-        if l.ltype() != r.ltype() {
-            return false;
+                match field {
+                    Some(val) => Ok(val),
+                    None => Self::bind(&instance.class.clone(), Value::Instance(instance), name),
+                }
+            }
+            other => builtins::property(&other, name.lexeme())
+                .map_err(|msg| Exception::new(msg, name.clone())),
         }
+    }
 
-        match l.ltype() {
-            LiteralType::Number => {
-                let l_val: &f64 = l.value().downcast_ref::<f64>().unwrap();
-                let r_val: &f64 = r.value().downcast_ref::<f64>().unwrap();
+    fn set(object: &Value, name: &Token, val: Value) -> Result<(), Exception> {
+        match *object {
+            Value::Instance(ref instance) => {
+                instance
+                    .fields
+                    .borrow_mut()
+                    .insert(name.lexeme().to_string(), val);
 
-                l_val == r_val
+                Ok(())
             }
-            LiteralType::String => {
-                let l_val: &String = l.value().downcast_ref::<String>().unwrap();
-                let r_val: &String = r.value().downcast_ref::<String>().unwrap();
+            _ => Err(Exception::new(
+                "Only instances have fields".to_string(),
+                name.clone(),
+            )),
+        }
+    }
 
-                l_val == r_val
+    // Evaluates the target's operands once, reads it, combines it with `value`
+    // (1 for postfix operators) and writes it back. Returns the old and the new
+    // value.
+    fn update(
+        &mut self,
+        target: &Expr,
+        op: &Token,
+        value: Option<&Rc<Expr>>,
+    ) -> Result<(Value, Value), Exception> {
+        let ttype: TokenType = match op.ttype() {
+            TokenType::PlusEqual | TokenType::PlusPlus => TokenType::Plus,
+            TokenType::MinusEqual | TokenType::MinusMinus => TokenType::Minus,
+            TokenType::StarEqual => TokenType::Star,
+            TokenType::SlashEqual => TokenType::Slash,
+            _ => unreachable!(),
+        };
+
+        match *target {
+            Expr::Var(ref name, ref depth) => {
+                let old: Value = self.scope.get(name.clone(), depth.get())?;
+                let rhs: Value = self.operand(value)?;
+                let new: Value = Self::binary(ttype, old.clone(), rhs, op)?;
+
+                self.scope.assign(name.clone(), depth.get(), new.clone())?;
+                Ok((old, new))
+            }
+            Expr::Get(ref object, ref name) => {
+                let object: Value = self.eval(object)?;
+                let old: Value = Self::get(object.clone(), name)?;
+                let rhs: Value = self.operand(value)?;
+                let new: Value = Self::binary(ttype, old.clone(), rhs, op)?;
+
+                Self::set(&object, name, new.clone())?;
+                Ok((old, new))
             }
-            _ => false,
+            Expr::Index(ref object, ref bracket, ref index) => {
+                let object: Value = self.eval(object)?;
+                let index: Value = self.eval(index)?;
+                let old: Value = builtins::get_index(&object, &index)
+                    .map_err(|msg| Exception::new(msg, bracket.clone()))?;
+                let rhs: Value = self.operand(value)?;
+                let new: Value = Self::binary(ttype, old.clone(), rhs, op)?;
+
+                builtins::set_index(&object, &index, new.clone())
+                    .map_err(|msg| Exception::new(msg, bracket.clone()))?;
+                Ok((old, new))
+            }
+            _ => unreachable!(),
         }
     }
 
-    fn unwrap_optional(
-        op: Option<Rc<dyn Literal>>,
-        token: Token,
-    ) -> Result<Rc<dyn Literal>, RuntimeError> {
-        Ok(op
-            .ok_or(RuntimeError::new(
-                "Expect operand, None provided".to_string(),
-                token.clone(),
-            ))?
-            .clone())
+    fn operand(&mut self, value: Option<&Rc<Expr>>) -> Result<Value, Exception> {
+        match value {
+            Some(v) => self.eval(v),
+            None => Ok(Value::Number(1.0)),
+        }
     }
 
-    fn unwrap_number(op: Rc<dyn Literal>, token: Token) -> Result<f64, RuntimeError> {
-        Ok(*op.value().downcast_ref::<f64>().ok_or(RuntimeError::new(
-            "Expect number".to_string(),
-            token.clone(),
-        ))?)
+    fn binary(
+        ttype: TokenType,
+        left: Value,
+        right: Value,
+        token: &Token,
+    ) -> Result<Value, Exception> {
+        match ttype {
+            TokenType::Minus => Ok(Value::Number(
+                Self::unwrap_number(&left, token)? - Self::unwrap_number(&right, token)?,
+            )),
+            TokenType::Slash => Ok(Value::Number(
+                Self::unwrap_number(&left, token)? / Self::unwrap_number(&right, token)?,
+            )),
+            TokenType::Star => Ok(Value::Number(
+                Self::unwrap_number(&left, token)? * Self::unwrap_number(&right, token)?,
+            )),
+            TokenType::Percent => Ok(Value::Number(builtins::modulo(
+                Self::unwrap_number(&left, token)?,
+                Self::unwrap_number(&right, token)?,
+            ))),
+            TokenType::TildeSlash => Ok(Value::Number(builtins::floor_divide(
+                Self::unwrap_number(&left, token)?,
+                Self::unwrap_number(&right, token)?,
+            ))),
+            TokenType::StarStar => Ok(Value::Number(
+                Self::unwrap_number(&left, token)?.powf(Self::unwrap_number(&right, token)?),
+            )),
+            TokenType::Greater => Ok(Value::Bool(
+                Self::unwrap_number(&left, token)? > Self::unwrap_number(&right, token)?,
+            )),
+            TokenType::GreaterEqual => Ok(Value::Bool(
+                Self::unwrap_number(&left, token)? >= Self::unwrap_number(&right, token)?,
+            )),
+            TokenType::Less => Ok(Value::Bool(
+                Self::unwrap_number(&left, token)? < Self::unwrap_number(&right, token)?,
+            )),
+            TokenType::LessEqual => Ok(Value::Bool(
+                Self::unwrap_number(&left, token)? <= Self::unwrap_number(&right, token)?,
+            )),
+            TokenType::Plus => match (left, right) {
+                (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
+                (Value::Str(l), Value::Str(r)) => {
+                    Ok(Value::Str(Rc::from(format!("{}{}", l, r).as_str())))
+                }
+                (Value::Str(_), _) => {
+                    Err(Exception::new("Expect String".to_string(), token.clone()))
+                }
+                _ => Err(Exception::new("Expect number".to_string(), token.clone())),
+            },
+            TokenType::EqualEqual => Ok(Value::Bool(left.is_equal(&right))),
+            TokenType::BangEqual => Ok(Value::Bool(!left.is_equal(&right))),
+            _ => unreachable!(),
+        }
     }
 
-    fn unwrap_string(op: Rc<dyn Literal>, token: Token) -> Result<String, RuntimeError> {
-        Ok(op
-            .value()
-            .downcast_ref::<String>()
-            .ok_or(RuntimeError::new(
-                "Expect String".to_string(),
-                token.clone(),
-            ))?
-            .to_string())
+    fn unwrap_number(op: &Value, token: &Token) -> Result<f64, Exception> {
+        match *op {
+            Value::Number(n) => Ok(n),
+            _ => Err(Exception::new("Expect number".to_string(), token.clone())),
+        }
     }
 }
@@ -5,19 +5,27 @@ use std::collections::HashMap;
 pub static KEYWORDS: Lazy<HashMap<String, TokenType>> = Lazy::new(|| {
     HashMap::from([
         (String::from("and"), TokenType::And),
+        (String::from("as"), TokenType::As),
+        (String::from("break"), TokenType::Break),
+        (String::from("catch"), TokenType::Catch),
         (String::from("class"), TokenType::Class),
+        (String::from("continue"), TokenType::Continue),
         (String::from("else"), TokenType::Else),
         (String::from("false"), TokenType::False),
+        (String::from("finally"), TokenType::Finally),
         (String::from("for"), TokenType::For),
         (String::from("fun"), TokenType::Fun),
         (String::from("if"), TokenType::If),
+        (String::from("import"), TokenType::Import),
         (String::from("nil"), TokenType::Nil),
         (String::from("or"), TokenType::Or),
         (String::from("print"), TokenType::Print),
         (String::from("return"), TokenType::Return),
         (String::from("super"), TokenType::Super),
         (String::from("this"), TokenType::This),
+        (String::from("throw"), TokenType::Throw),
         (String::from("true"), TokenType::True),
+        (String::from("try"), TokenType::Try),
         (String::from("var"), TokenType::Var),
         (String::from("while"), TokenType::While),
     ])
@@ -0,0 +1,24 @@
+mod ast_printer;
+mod builtins;
+mod chunk;
+mod compiler;
+mod environment;
+mod error;
+mod expr;
+mod interpreter;
+mod keyword;
+mod lox;
+mod module;
+mod parser;
+mod resolver;
+mod scanner;
+mod scope;
+mod stack;
+mod stmt;
+mod token;
+mod value;
+mod vm;
+
+pub use crate::error::{CompileError, Error, ParseError, ResolveError, RuntimeError, ScanError};
+pub use crate::lox::{Backend, Lox};
+pub use crate::value::Value;
@@ -0,0 +1,186 @@
+use crate::ast_printer::AstPrinter;
+use crate::compiler::Compiler;
+use crate::error::Error;
+use crate::interpreter::Interpreter;
+use crate::module;
+use crate::scanner::Scanner;
+use crate::stmt::Stmt;
+use crate::token::Token;
+use crate::value::{Native, Value};
+use crate::vm::Vm;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Backend {
+    TreeWalker,
+    Vm,
+}
+
+enum Runtime {
+    TreeWalker(Interpreter),
+    Vm(Vm),
+}
+
+// Keeps its globals between runs, so a host can feed it source piece by
+// piece the way the prompt does.
+pub struct Lox {
+    runtime: Runtime,
+    colour: bool,
+}
+
+impl Lox {
+    pub fn new(backend: Backend) -> Lox {
+        let runtime: Runtime = match backend {
+            Backend::TreeWalker => Runtime::TreeWalker(Interpreter::new()),
+            Backend::Vm => Runtime::Vm(Vm::new()),
+        };
+
+        Lox {
+            runtime,
+            colour: false,
+        }
+    }
+
+    // The error is also reported to the diagnostic sink, so hosts that only
+    // care whether the run failed don't have to print it themselves.
+    pub fn run(&mut self, src: &str) -> Result<(), Error> {
+        let res: Result<(), Error> = self.execute(src);
+
+        self.report(res)
+    }
+
+    // Runs every static check the backend would, without running anything.
+    pub fn check(&mut self, src: &str) -> Result<(), Error> {
+        let res: Result<(), Error> =
+            module::parse(src.to_string(), None).and_then(|statements| match self.runtime {
+                Runtime::TreeWalker(_) => Ok(()),
+                Runtime::Vm(_) => Compiler::new()
+                    .compile(&statements)
+                    .map(|_| ())
+                    .map_err(Error::Compile),
+            });
+
+        self.report(res)
+    }
+
+    // One token per line, as `line:column Type lexeme`.
+    pub fn tokens(&mut self, src: &str) -> Result<String, Error> {
+        let mut scanner: Scanner = Scanner::new(src.to_string(), None);
+        let res: Result<String, Error> = scanner
+            .scan_tokens()
+            .map(|tokens| {
+                let lines: Vec<String> = tokens.iter().map(describe).collect();
+                lines.join("\n")
+            })
+            .map_err(Error::Scan);
+
+        self.report(res)
+    }
+
+    // The resolved syntax tree, one top-level statement per line.
+    pub fn ast(&mut self, src: &str) -> Result<String, Error> {
+        let res: Result<String, Error> = module::parse(src.to_string(), None)
+            .map(|statements| AstPrinter::print_stmts(&statements));
+
+        self.report(res)
+    }
+
+    fn report<T>(&mut self, res: Result<T, Error>) -> Result<T, Error> {
+        if let Err(ref e) = res {
+            let report: String = e.render(self.colour);
+            let _ = writeln!(self.diagnostics(), "{}", report);
+        }
+
+        res
+    }
+
+    fn execute(&mut self, src: &str) -> Result<(), Error> {
+        let statements: Vec<Stmt> = module::parse(src.to_string(), None)?;
+
+        match self.runtime {
+            Runtime::TreeWalker(ref mut interpreter) => interpreter.interpret(statements),
+            Runtime::Vm(ref mut vm) => vm.interpret(&statements),
+        }
+    }
+
+    // Where `print` writes to, stdout by default.
+    pub fn set_output<W: Write + 'static>(&mut self, out: W) {
+        match self.runtime {
+            Runtime::TreeWalker(ref mut interpreter) => interpreter.set_output(Box::new(out)),
+            Runtime::Vm(ref mut vm) => vm.set_output(Box::new(out)),
+        }
+    }
+
+    // Where errors are reported to, stderr by default.
+    pub fn set_diagnostics<W: Write + 'static>(&mut self, diagnostics: W) {
+        match self.runtime {
+            Runtime::TreeWalker(ref mut interpreter) => {
+                interpreter.set_diagnostics(Box::new(diagnostics))
+            }
+            Runtime::Vm(ref mut vm) => vm.set_diagnostics(Box::new(diagnostics)),
+        }
+    }
+
+    // Whether reported errors use ANSI colours, off by default.
+    pub fn set_colour(&mut self, colour: bool) {
+        self.colour = colour;
+    }
+
+    fn diagnostics(&mut self) -> &mut dyn Write {
+        match self.runtime {
+            Runtime::TreeWalker(ref mut interpreter) => interpreter.diagnostics(),
+            Runtime::Vm(ref mut vm) => vm.diagnostics(),
+        }
+    }
+
+    // Imports in the source that runs next resolve relative to `path`.
+    pub fn set_path(&mut self, path: &Path) {
+        match self.runtime {
+            Runtime::TreeWalker(ref mut interpreter) => interpreter.set_path(path),
+            Runtime::Vm(ref mut vm) => vm.set_path(path),
+        }
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        match self.runtime {
+            Runtime::TreeWalker(ref interpreter) => interpreter.get_global(name),
+            Runtime::Vm(ref vm) => vm.get_global(name),
+        }
+    }
+
+    pub fn set_global(&mut self, name: &str, value: impl Into<Value>) {
+        match self.runtime {
+            Runtime::TreeWalker(ref mut interpreter) => interpreter.set_global(name, value.into()),
+            Runtime::Vm(ref mut vm) => vm.set_global(name, value.into()),
+        }
+    }
+
+    pub fn define_native<F>(&mut self, name: &str, arity: usize, fun: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, String> + 'static,
+    {
+        let native: Native = Native {
+            name: name.to_string(),
+            arity,
+            fun: Box::new(fun),
+        };
+
+        match self.runtime {
+            Runtime::TreeWalker(ref mut interpreter) => interpreter.define_native(native),
+            Runtime::Vm(ref mut vm) => vm.define_native(native),
+        }
+    }
+}
+
+fn describe(token: &Token) -> String {
+    let line: String = format!(
+        "{}:{} {:?} {}",
+        token.line(),
+        token.column(),
+        token.ttype(),
+        token.lexeme()
+    );
+
+    line.trim_end().to_string()
+}
@@ -1,58 +1,222 @@
-use std::cmp::Ordering;
+use rlox::{Backend, Error, Lox};
 use std::env;
 use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::Path;
 use std::process::exit;
 
-mod ast_printer;
-mod environment;
-mod error;
-mod expr;
-mod interpreter;
-mod keyword;
-mod literal;
-mod parser;
-mod scanner;
-mod scope;
-mod stmt;
-mod token;
+const USAGE: &str = "\
+Usage: rlox [options] [run] <script | -> [args...]
+       rlox [options] [run] -e <code> [args...]
+       rlox [options] check <script | - | -e <code>>
+       rlox [options] [repl]
 
-use crate::interpreter::Interpreter;
-use crate::parser::Parser;
-use crate::scanner::Scanner;
-use crate::token::Token;
+With no script and stdin not a terminal, the script is read from stdin.
 
-fn run(interpreter: &mut Interpreter, src: String) {
-    let mut scanner: Scanner = Scanner::new(src);
-    let tokens: &Vec<Token> = scanner.scan_tokens();
-    let mut parser: Parser = Parser::new(tokens);
+Options:
+  --vm       Run on the bytecode VM instead of the tree-walker
+  --tokens   Print the tokens of the source instead of running it
+  --ast      Print the syntax tree of the source instead of running it
+  -h, --help Print this message";
 
-    interpreter.interpret(parser.parse())
+// Exit codes follow the BSD sysexits.h convention, as the reference Lox
+// implementation does.
+const EX_USAGE: i32 = 64;
+const EX_DATAERR: i32 = 65;
+const EX_NOINPUT: i32 = 66;
+const EX_SOFTWARE: i32 = 70;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Command {
+    Run,
+    Check,
+    Repl,
+}
+
+enum Source {
+    File(String),
+    Stdin,
+    Inline(String),
+}
+
+struct Cli {
+    command: Command,
+    backend: Backend,
+    tokens: bool,
+    ast: bool,
+    source: Option<Source>,
+    args: Vec<String>,
 }
 
-fn run_file(interpreter: &mut Interpreter, path: &String) {
-    let content = fs::read_to_string(path).expect("Read error!");
+impl Cli {
+    // Options come before the source; everything after it is passed on to
+    // the script as `args`. The prompt needs a terminal to read from.
+    fn parse(args: &[String], interactive: bool) -> Result<Option<Cli>, String> {
+        let mut command: Option<Command> = None;
+        let mut cli: Cli = Cli {
+            command: Command::Repl,
+            backend: Backend::TreeWalker,
+            tokens: false,
+            ast: false,
+            source: None,
+            args: Vec::new(),
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--vm" => cli.backend = Backend::Vm,
+                "--tokens" => cli.tokens = true,
+                "--ast" => cli.ast = true,
+                "-e" => {
+                    let code: &String = args.next().ok_or("Expect code after '-e'")?;
+                    cli.source = Some(Source::Inline(code.clone()));
+                    break;
+                }
+                "-" => {
+                    cli.source = Some(Source::Stdin);
+                    break;
+                }
+                "run" if command.is_none() => command = Some(Command::Run),
+                "check" if command.is_none() => command = Some(Command::Check),
+                "repl" if command.is_none() => command = Some(Command::Repl),
+                option if option.starts_with('-') => {
+                    return Err(format!("Unknown option '{}'", option));
+                }
+                path => {
+                    cli.source = Some(Source::File(path.to_string()));
+                    break;
+                }
+            }
+        }
+        cli.args = args.cloned().collect();
+
+        cli.command = match (command, &cli.source) {
+            (Some(Command::Repl), Some(_)) => {
+                return Err("'repl' doesn't take a script".to_string())
+            }
+            (Some(Command::Check), Some(_)) if !cli.args.is_empty() => {
+                return Err("'check' doesn't take script arguments".to_string());
+            }
+            (Some(command), None) if command != Command::Repl => {
+                return Err("Expect a script, '-' or '-e <code>'".to_string());
+            }
+            (Some(Command::Repl), None) if !interactive => {
+                return Err("'repl' needs stdin to be a terminal".to_string());
+            }
+            (Some(command), _) => command,
+            (None, Some(_)) => Command::Run,
+            (None, None) if !interactive => {
+                cli.source = Some(Source::Stdin);
+                Command::Run
+            }
+            (None, None) => Command::Repl,
+        };
+
+        if cli.command == Command::Repl && (cli.tokens || cli.ast) {
+            return Err("'--tokens' and '--ast' need a script".to_string());
+        }
 
-    run(interpreter, content);
+        Ok(Some(cli))
+    }
 }
 
-fn run_prompt(interpreter: &mut Interpreter) {
-    loop {
-        let src = rprompt::prompt_reply("> ").unwrap();
+fn read(lox: &mut Lox, source: &Source) -> io::Result<String> {
+    match *source {
+        Source::File(ref path) => {
+            let src: String = fs::read_to_string(path)?;
+            lox.set_path(Path::new(path));
+            Ok(src)
+        }
+        Source::Stdin => {
+            let mut src: String = String::new();
+            io::stdin().read_to_string(&mut src)?;
+            Ok(src)
+        }
+        Source::Inline(ref code) => Ok(code.clone()),
+    }
+}
 
-        run(interpreter, src)
+fn exit_code<T>(res: Result<T, Error>) -> i32 {
+    match res {
+        Ok(_) => 0,
+        Err(Error::Runtime(_)) => EX_SOFTWARE,
+        Err(_) => EX_DATAERR,
+    }
+}
+
+fn dump(res: Result<String, Error>) -> i32 {
+    if let Ok(ref dump) = res {
+        println!("{}", dump);
+    }
+
+    exit_code(res)
+}
+
+fn run_prompt(lox: &mut Lox) {
+    // Errors are reported but don't end the session; end of input does.
+    while let Ok(src) = rprompt::prompt_reply("> ") {
+        let _ = lox.run(&src);
     }
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let mut interpreter: Interpreter = Interpreter::new();
+    exit(run_cli());
+}
+
+fn run_cli() -> i32 {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let cli: Cli = match Cli::parse(&args, io::stdin().is_terminal()) {
+        Ok(Some(cli)) => cli,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        Err(msg) => {
+            eprintln!("{}\n\n{}", msg, USAGE);
+            return EX_USAGE;
+        }
+    };
+
+    let mut lox: Lox = Lox::new(cli.backend);
+    lox.set_colour(io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none());
+
+    let source: &Source = match cli.source {
+        Some(ref source) => source,
+        None => {
+            run_prompt(&mut lox);
+            return 0;
+        }
+    };
+
+    let src: String = match read(&mut lox, source) {
+        Ok(src) => src,
+        Err(e) => {
+            let name: &str = match *source {
+                Source::File(ref path) => path,
+                _ => "stdin",
+            };
+            eprintln!("Could not read '{}': {}", name, e);
+            return EX_NOINPUT;
+        }
+    };
+
+    if cli.tokens {
+        let code: i32 = dump(lox.tokens(&src));
+        if code != 0 || !cli.ast {
+            return code;
+        }
+    }
+    if cli.ast {
+        return dump(lox.ast(&src));
+    }
 
-    match args.len().cmp(&2) {
-        Ordering::Greater => {
-            println!("Usage: rlox [script]");
-            exit(64);
+    match cli.command {
+        Command::Check => exit_code(lox.check(&src)),
+        _ => {
+            lox.set_global("args", cli.args);
+            exit_code(lox.run(&src))
         }
-        Ordering::Equal => run_file(&mut interpreter, &args[1]),
-        Ordering::Less => run_prompt(&mut interpreter),
     }
 }
@@ -0,0 +1,125 @@
+use crate::error::{Error, Exception};
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanner::Scanner;
+use crate::stmt::Stmt;
+use crate::token::Token;
+use crate::value::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn parse(src: String, name: Option<String>) -> Result<Vec<Stmt>, Error> {
+    let mut scanner: Scanner = Scanner::new(src, name);
+    let tokens: &Vec<Token> = scanner.scan_tokens().map_err(Error::Scan)?;
+    let mut parser: Parser = Parser::new(tokens);
+    let statements: Vec<Stmt> = parser.parse().map_err(Error::Parse)?;
+
+    let mut resolver: Resolver = Resolver::new();
+    resolver.resolve(&statements).map_err(Error::Resolve)?;
+
+    Ok(statements)
+}
+
+// Tracks the files being run, innermost last, so that imports resolve
+// relative to the file that contains them and cycles can be reported.
+pub struct Modules {
+    cache: HashMap<PathBuf, Value>,
+    loading: Vec<PathBuf>,
+}
+
+impl Modules {
+    pub fn new() -> Modules {
+        Modules {
+            cache: HashMap::new(),
+            loading: Vec::new(),
+        }
+    }
+
+    pub fn set_main(&mut self, path: &Path) {
+        self.loading = vec![canonical(path.to_path_buf())];
+    }
+
+    // Without a main script, as in the prompt, paths are taken as given.
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        let path: PathBuf = match self.loading.last().and_then(|p| p.parent()) {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        };
+
+        canonical(path)
+    }
+
+    pub fn get(&self, path: &Path) -> Option<Value> {
+        self.cache.get(path).cloned()
+    }
+
+    pub fn begin(&mut self, path: PathBuf, token: &Token) -> Result<Vec<Stmt>, Exception> {
+        if let Some(start) = self.loading.iter().position(|p| *p == path) {
+            let cycle: Vec<String> = self.loading[start..]
+                .iter()
+                .chain([&path])
+                .map(|p| file_name(p))
+                .collect();
+
+            return Err(Exception::new(
+                format!("Import cycle: {}", cycle.join(" -> ")),
+                token.clone(),
+            ));
+        }
+
+        let src: String = fs::read_to_string(&path).map_err(|_| {
+            Exception::new(
+                format!("Could not read module '{}'", path.display()),
+                token.clone(),
+            )
+        })?;
+
+        match parse(src, Some(file_name(&path))) {
+            Ok(statements) => {
+                self.loading.push(path);
+                Ok(statements)
+            }
+            Err(e) => Err(invalid(&path, token).with_cause(e)),
+        }
+    }
+
+    // Modules that failed to run are not cached, so importing one again
+    // reports the error again instead of handing out a half-built module.
+    pub fn finish(&mut self, module: Option<Value>) {
+        let path: PathBuf = self.loading.pop().unwrap();
+
+        if let Some(module) = module {
+            self.cache.insert(path, module);
+        }
+    }
+
+    pub fn fail(&mut self, token: &Token, e: Error) -> Exception {
+        let path: PathBuf = self.loading.pop().unwrap();
+
+        invalid(&path, token).with_cause(e)
+    }
+}
+
+pub fn name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn invalid(path: &Path, token: &Token) -> Exception {
+    Exception::new(
+        format!("Module '{}' has errors", file_name(path)),
+        token.clone(),
+    )
+}
+
+fn canonical(path: PathBuf) -> PathBuf {
+    path.canonicalize().unwrap_or(path)
+}
@@ -1,24 +1,36 @@
 use crate::error::ParseError;
 use crate::expr::Expr;
-use crate::literal::*;
+use crate::stack;
 use crate::stmt::Stmt;
 use crate::token::{Token, TokenType};
+use crate::value::Value;
+use std::cell::Cell;
 use std::rc::Rc;
 
+// Every level of nesting is a level of the syntax tree, which is freed
+// recursively, so how deep a script may nest is capped.
+const NESTING_MAX: usize = 1000;
+
 pub struct Parser {
     current: usize,
+    depth: usize,
+    too_deep: bool,
     tokens: Vec<Token>,
+    errors: Vec<ParseError>,
 }
 
 impl Parser {
     pub fn new(tokens: &[Token]) -> Parser {
         Parser {
             current: 0,
+            depth: 0,
+            too_deep: false,
             tokens: tokens.to_vec(),
+            errors: Vec::new(),
         }
     }
 
-    pub fn parse(&mut self) -> Vec<Stmt> {
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
         let mut statements: Vec<Stmt> = Vec::new();
 
         while !self.is_at_end() {
@@ -27,7 +39,11 @@ impl Parser {
             }
         }
 
-        statements
+        if self.errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
     }
 
     fn previous(&self) -> Token {
@@ -79,25 +95,91 @@ impl Parser {
     }
 
     fn declaration(&mut self) -> Option<Stmt> {
-        if self.match_token(&[TokenType::Var]) {
-            match self.var_declaration() {
-                Ok(s) => Some(s),
-                Err(e) => {
-                    self.show_error(e);
-                    self.sync();
-                    None
-                }
-            }
+        let res: Result<Stmt, ParseError> = if self.match_token(&[TokenType::Class]) {
+            self.class_declaration()
+        } else if self.match_token(&[TokenType::Fun]) {
+            self.function("function")
+        } else if self.match_token(&[TokenType::Import]) {
+            self.import_declaration()
+        } else if self.match_token(&[TokenType::Var]) {
+            self.var_declaration()
         } else {
-            match self.statement() {
-                Ok(s) => Some(s),
-                Err(e) => {
-                    self.show_error(e);
-                    self.sync();
-                    None
+            self.statement()
+        };
+
+        match res {
+            Ok(s) => Some(s),
+            Err(e) => {
+                self.error(e);
+                self.sync();
+                None
+            }
+        }
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name: Token = self.consume(TokenType::Identifier, "Expect class name")?;
+
+        let mut superclass: Option<Expr> = None;
+        if self.match_token(&[TokenType::Less]) {
+            let super_name: Token =
+                self.consume(TokenType::Identifier, "Expect superclass name")?;
+
+            superclass = Some(Expr::Var(super_name, Cell::new(None)));
+        }
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body")?;
+
+        let mut methods: Vec<Stmt> = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function("method")?);
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after class body")?;
+
+        Ok(Stmt::Class(name, superclass, methods))
+    }
+
+    fn function(&mut self, kind: &str) -> Result<Stmt, ParseError> {
+        let name: Token = self.consume(TokenType::Identifier, &format!("Expect {} name", kind))?;
+        self.consume(
+            TokenType::LeftParen,
+            &format!("Expect '(' after {} name", kind),
+        )?;
+
+        let mut params: Vec<Token> = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if params.len() >= 255 {
+                    self.error(ParseError::new(
+                        "Can't have more than 255 parameters".to_string(),
+                        self.peek(),
+                    ));
+                }
+                params.push(self.consume(TokenType::Identifier, "Expect parameter name")?);
+
+                if !self.match_token(&[TokenType::Comma]) {
+                    break;
                 }
             }
         }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters")?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {} body", kind),
+        )?;
+        let body: Vec<Stmt> = self.block()?;
+
+        Ok(Stmt::Function(name, params, Rc::new(body)))
+    }
+
+    fn import_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let path: Token = self.consume(TokenType::String, "Expect module path after 'import'")?;
+        self.consume(TokenType::As, "Expect 'as' after module path")?;
+        let name: Token = self.consume(TokenType::Identifier, "Expect module name after 'as'")?;
+        self.consume(TokenType::Semicolon, "Expect ';' after import")?;
+
+        Ok(Stmt::Import(path, name))
     }
 
     fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
@@ -113,19 +195,35 @@ impl Parser {
     }
 
     fn statement(&mut self) -> Result<Stmt, ParseError> {
-        if self.match_token(&[TokenType::For]) {
-            self.for_statement()
-        } else if self.match_token(&[TokenType::If]) {
-            self.if_statement()
-        } else if self.match_token(&[TokenType::Print]) {
-            self.print_statement()
-        } else if self.match_token(&[TokenType::While]) {
-            self.while_statement()
-        } else if self.match_token(&[TokenType::LeftBrace]) {
-            Ok(Stmt::Block(self.block()?))
-        } else {
-            self.expression_statement()
-        }
+        self.nest()?;
+        let res: Result<Stmt, ParseError> = stack::guard(|| {
+            if self.match_token(&[TokenType::Break]) {
+                self.break_statement()
+            } else if self.match_token(&[TokenType::Continue]) {
+                self.continue_statement()
+            } else if self.match_token(&[TokenType::For]) {
+                self.for_statement()
+            } else if self.match_token(&[TokenType::If]) {
+                self.if_statement()
+            } else if self.match_token(&[TokenType::Print]) {
+                self.print_statement()
+            } else if self.match_token(&[TokenType::Return]) {
+                self.return_statement()
+            } else if self.match_token(&[TokenType::Throw]) {
+                self.throw_statement()
+            } else if self.match_token(&[TokenType::Try]) {
+                self.try_statement()
+            } else if self.match_token(&[TokenType::While]) {
+                self.while_statement()
+            } else if self.match_token(&[TokenType::LeftBrace]) {
+                Ok(Stmt::Block(self.block()?))
+            } else {
+                self.expression_statement()
+            }
+        });
+        self.depth -= 1;
+
+        res
     }
 
     fn if_statement(&mut self) -> Result<Stmt, ParseError> {
@@ -163,16 +261,80 @@ impl Parser {
         Ok(Stmt::Print(expr))
     }
 
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword: Token = self.previous();
+        let value: Option<Expr> = if !self.check(TokenType::Semicolon) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after return value")?;
+
+        Ok(Stmt::Return(keyword, value))
+    }
+
+    fn break_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword: Token = self.previous();
+        self.consume(TokenType::Semicolon, "Expect ';' after 'break'")?;
+
+        Ok(Stmt::Break(keyword))
+    }
+
+    fn continue_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword: Token = self.previous();
+        self.consume(TokenType::Semicolon, "Expect ';' after 'continue'")?;
+
+        Ok(Stmt::Continue(keyword))
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword: Token = self.previous();
+        let value: Expr = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after thrown value")?;
+
+        Ok(Stmt::Throw(keyword, value))
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(TokenType::LeftBrace, "Expect '{' after 'try'")?;
+        let body: Vec<Stmt> = self.block()?;
+
+        let mut catch: Option<(Token, Vec<Stmt>)> = None;
+        if self.match_token(&[TokenType::Catch]) {
+            self.consume(TokenType::LeftParen, "Expect '(' after 'catch'")?;
+            let name: Token = self.consume(TokenType::Identifier, "Expect exception name")?;
+            self.consume(TokenType::RightParen, "Expect ')' after exception name")?;
+            self.consume(TokenType::LeftBrace, "Expect '{' before catch body")?;
+            catch = Some((name, self.block()?));
+        }
+
+        let mut finally: Option<Rc<Vec<Stmt>>> = None;
+        if self.match_token(&[TokenType::Finally]) {
+            self.consume(TokenType::LeftBrace, "Expect '{' after 'finally'")?;
+            finally = Some(Rc::new(self.block()?));
+        }
+
+        if catch.is_none() && finally.is_none() {
+            return Err(ParseError::new(
+                "Expect 'catch' or 'finally' after try block".to_string(),
+                self.peek(),
+            ));
+        }
+
+        Ok(Stmt::Try(body, catch, finally))
+    }
+
     fn while_statement(&mut self) -> Result<Stmt, ParseError> {
         self.consume(TokenType::LeftParen, "Expect '(' after 'while'")?;
         let condition: Expr = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after condition")?;
         let body: Stmt = self.statement()?;
 
-        Ok(Stmt::While(condition, Rc::new(body)))
+        Ok(Stmt::While(condition, Rc::new(body), None))
     }
 
     fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword: Token = self.previous();
         let _ = self.consume(TokenType::LeftParen, "Expect '(' after 'for'")?;
 
         let initializer: Option<Stmt>;
@@ -198,22 +360,13 @@ impl Parser {
 
         let mut body: Stmt = self.statement()?;
 
-        if increment.is_some() {
-            let mut stmts: Vec<Stmt> = Vec::new();
-            stmts.push(body);
-            stmts.push(Stmt::Expr(increment.unwrap()));
-            body = Stmt::Block(stmts);
-        }
         if condition.is_none() {
-            condition = Some(Expr::Literal(Some(Rc::new(BoolLiteral::new(true)))));
+            condition = Some(Expr::Literal(keyword, Value::Bool(true)));
         }
-        body = Stmt::While(condition.unwrap(), Rc::new(body));
+        body = Stmt::While(condition.unwrap(), Rc::new(body), increment);
 
-        if initializer.is_some() {
-            let mut stmts: Vec<Stmt> = Vec::new();
-            stmts.push(initializer.unwrap());
-            stmts.push(body);
-            body = Stmt::Block(stmts);
+        if let Some(init) = initializer {
+            body = Stmt::Block(vec![init, body]);
         }
 
         Ok(body)
@@ -231,23 +384,85 @@ impl Parser {
     }
 
     fn assignment(&mut self) -> Result<Expr, ParseError> {
-        let expr: Expr = self.or()?;
+        let expr: Expr = self.conditional()?;
 
         if self.match_token(&[TokenType::Equal]) {
             let equals: Token = self.previous();
-            let val: Expr = self.assignment()?;
+            let val: Expr = self.nested(Self::assignment)?;
 
             match expr {
-                Expr::Var(ref token) => return Ok(Expr::Assign(token.clone(), Rc::new(val))),
-                _ => {
-                    return Err(ParseError::new(
-                        "Invalid assignment target".to_string(),
-                        equals,
+                Expr::Var(ref token, _) => {
+                    return Ok(Expr::Assign(token.clone(), Rc::new(val), Cell::new(None)))
+                }
+                Expr::Get(ref object, ref name) => {
+                    return Ok(Expr::Set(object.clone(), name.clone(), Rc::new(val)))
+                }
+                Expr::Index(ref object, ref bracket, ref index) => {
+                    return Ok(Expr::SetIndex(
+                        object.clone(),
+                        bracket.clone(),
+                        index.clone(),
+                        Rc::new(val),
                     ))
                 }
+                _ => {
+                    return Err(
+                        ParseError::new("Invalid assignment target".to_string(), equals)
+                            .with_span(expr.span()),
+                    )
+                }
             }
         }
 
+        if self.match_token(&[
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+        ]) {
+            let operator: Token = self.previous();
+            let val: Expr = self.nested(Self::assignment)?;
+
+            return Self::compound(expr, operator, val);
+        }
+
+        Ok(expr)
+    }
+
+    fn compound(target: Expr, operator: Token, val: Expr) -> Result<Expr, ParseError> {
+        Self::check_target(&target, &operator)?;
+
+        Ok(Expr::Compound(Rc::new(target), operator, Rc::new(val)))
+    }
+
+    fn check_target(target: &Expr, operator: &Token) -> Result<(), ParseError> {
+        match *target {
+            Expr::Var(..) | Expr::Get(..) | Expr::Index(..) => Ok(()),
+            _ => Err(
+                ParseError::new("Invalid assignment target".to_string(), operator.clone())
+                    .with_span(target.span()),
+            ),
+        }
+    }
+
+    fn conditional(&mut self) -> Result<Expr, ParseError> {
+        let expr: Expr = self.or()?;
+
+        if self.match_token(&[TokenType::Question]) {
+            let then_expr: Expr = self.expression()?;
+            self.consume(
+                TokenType::Colon,
+                "Expect ':' after then branch of conditional",
+            )?;
+            let else_expr: Expr = self.nested(Self::conditional)?;
+
+            return Ok(Expr::Conditional(
+                Rc::new(expr),
+                Rc::new(then_expr),
+                Rc::new(else_expr),
+            ));
+        }
+
         Ok(expr)
     }
 
@@ -321,7 +536,12 @@ impl Parser {
     fn factor(&mut self) -> Result<Expr, ParseError> {
         let mut expr: Expr = self.unary()?;
 
-        while self.match_token(&[TokenType::Slash, TokenType::Star]) {
+        while self.match_token(&[
+            TokenType::Slash,
+            TokenType::Star,
+            TokenType::Percent,
+            TokenType::TildeSlash,
+        ]) {
             let operator: Token = self.previous();
             let r_expr = self.unary()?;
             expr = Expr::Binary(Rc::new(expr), operator, Rc::new(r_expr));
@@ -331,53 +551,172 @@ impl Parser {
     }
 
     fn unary(&mut self) -> Result<Expr, ParseError> {
-        if self.match_token(&[TokenType::Bang, TokenType::Minus]) {
+        self.nest()?;
+        let res: Result<Expr, ParseError> = stack::guard(|| {
+            if self.match_token(&[TokenType::Bang, TokenType::Minus]) {
+                let operator: Token = self.previous();
+                let r_expr = self.unary()?;
+
+                return Ok(Expr::Unary(operator, Rc::new(r_expr)));
+            }
+            if self.match_token(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+                let operator: Token = self.previous();
+                let target: Expr = self.unary()?;
+
+                let one: Expr = Expr::Literal(operator.clone(), Value::Number(1.0));
+
+                return Self::compound(target, operator, one);
+            }
+
+            self.power()
+        });
+        self.depth -= 1;
+
+        res
+    }
+
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let expr: Expr = self.postfix()?;
+
+        if self.match_token(&[TokenType::StarStar]) {
             let operator: Token = self.previous();
-            let r_expr = self.unary()?;
+            let r_expr: Expr = self.unary()?;
+
+            return Ok(Expr::Binary(Rc::new(expr), operator, Rc::new(r_expr)));
+        }
+
+        Ok(expr)
+    }
+
+    fn postfix(&mut self) -> Result<Expr, ParseError> {
+        let expr: Expr = self.call()?;
+
+        if self.match_token(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator: Token = self.previous();
+            Self::check_target(&expr, &operator)?;
+
+            return Ok(Expr::Postfix(Rc::new(expr), operator));
+        }
+
+        Ok(expr)
+    }
+
+    fn call(&mut self) -> Result<Expr, ParseError> {
+        let mut expr: Expr = self.primary()?;
+
+        loop {
+            if self.match_token(&[TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_token(&[TokenType::Dot]) {
+                let name: Token =
+                    self.consume(TokenType::Identifier, "Expect property name after '.'")?;
+                expr = Expr::Get(Rc::new(expr), name);
+            } else if self.match_token(&[TokenType::LeftBracket]) {
+                let index: Expr = self.expression()?;
+                let bracket: Token =
+                    self.consume(TokenType::RightBracket, "Expect ']' after index")?;
+                expr = Expr::Index(Rc::new(expr), bracket, Rc::new(index));
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
 
-            return Ok(Expr::Unary(operator, Rc::new(r_expr)));
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+        let mut args: Vec<Expr> = Vec::new();
+
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if args.len() >= 255 {
+                    self.error(ParseError::new(
+                        "Can't have more than 255 arguments".to_string(),
+                        self.peek(),
+                    ));
+                }
+                args.push(self.expression()?);
+
+                if !self.match_token(&[TokenType::Comma]) {
+                    break;
+                }
+            }
         }
+        let paren: Token = self.consume(TokenType::RightParen, "Expect ')' after arguments")?;
 
-        self.primary()
+        Ok(Expr::Call(Rc::new(callee), paren, args))
     }
 
     fn primary(&mut self) -> Result<Expr, ParseError> {
         if self.match_token(&[TokenType::False]) {
-            return Ok(Expr::Literal(Some(Rc::new(BoolLiteral::new(false)))));
+            return Ok(Expr::Literal(self.previous(), Value::Bool(false)));
         }
         if self.match_token(&[TokenType::True]) {
-            return Ok(Expr::Literal(Some(Rc::new(BoolLiteral::new(true)))));
+            return Ok(Expr::Literal(self.previous(), Value::Bool(true)));
         }
         if self.match_token(&[TokenType::Nil]) {
-            return Ok(Expr::Literal(None));
+            return Ok(Expr::Literal(self.previous(), Value::Nil));
         }
-        if self.match_token(&[TokenType::Number]) {
-            let val: f64 = *self
-                .previous()
-                .literal()
-                .clone()
-                .unwrap()
-                .downcast::<f64>()
-                .ok()
-                .ok_or(ParseError::new("Expect number".to_string(), self.peek()))?;
+        if self.match_token(&[TokenType::Number, TokenType::String]) {
+            let val: Value = self.previous().literal().clone().ok_or(ParseError::new(
+                "Expect literal".to_string(),
+                self.previous(),
+            ))?;
 
-            return Ok(Expr::Literal(Some(Rc::new(NumberLiteral::new(val)))));
+            return Ok(Expr::Literal(self.previous(), val));
         }
-        if self.match_token(&[TokenType::Number, TokenType::String]) {
-            let val: String = self
-                .previous()
-                .literal()
-                .clone()
-                .unwrap()
-                .downcast::<String>()
-                .ok()
-                .ok_or(ParseError::new("Expect string".to_string(), self.peek()))?
-                .to_string();
-
-            return Ok(Expr::Literal(Some(Rc::new(StrLiteral::new(val)))));
+        if self.match_token(&[TokenType::Interpolation]) {
+            return self.interpolation();
+        }
+        if self.match_token(&[TokenType::Super]) {
+            let keyword: Token = self.previous();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'")?;
+            let method: Token =
+                self.consume(TokenType::Identifier, "Expect superclass method name")?;
+
+            return Ok(Expr::Super(keyword, method, Cell::new(None)));
+        }
+        if self.match_token(&[TokenType::This]) {
+            return Ok(Expr::This(self.previous(), Cell::new(None)));
         }
         if self.match_token(&[TokenType::Identifier]) {
-            return Ok(Expr::Var(self.previous()));
+            return Ok(Expr::Var(self.previous(), Cell::new(None)));
+        }
+        if self.match_token(&[TokenType::LeftBracket]) {
+            let mut elements: Vec<Expr> = Vec::new();
+
+            if !self.check(TokenType::RightBracket) {
+                loop {
+                    elements.push(self.expression()?);
+
+                    if !self.match_token(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            let bracket: Token =
+                self.consume(TokenType::RightBracket, "Expect ']' after list elements")?;
+
+            return Ok(Expr::List(bracket, elements));
+        }
+        if self.match_token(&[TokenType::LeftBrace]) {
+            let mut entries: Vec<(Expr, Expr)> = Vec::new();
+
+            if !self.check(TokenType::RightBrace) {
+                loop {
+                    let key: Expr = self.expression()?;
+                    self.consume(TokenType::Colon, "Expect ':' after map key")?;
+                    entries.push((key, self.expression()?));
+
+                    if !self.match_token(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+            }
+            let brace: Token =
+                self.consume(TokenType::RightBrace, "Expect '}' after map entries")?;
+
+            return Ok(Expr::Map(brace, entries));
         }
         if self.match_token(&[TokenType::LeftParen]) {
             let expr: Expr = self.expression()?;
@@ -392,6 +731,75 @@ impl Parser {
         ))
     }
 
+    fn interpolation(&mut self) -> Result<Expr, ParseError> {
+        let plus: Token = self.previous().synthetic(TokenType::Plus, "+");
+        let mut parts: Vec<Expr> = Vec::new();
+
+        loop {
+            if let Some(segment) = Self::segment(&self.previous()) {
+                parts.push(segment);
+            }
+            parts.push(Expr::Stringify(Rc::new(self.expression()?)));
+
+            if !self.match_token(&[TokenType::Interpolation]) {
+                break;
+            }
+        }
+        self.consume(
+            TokenType::InterpolationEnd,
+            "Expect '}' after interpolated expression",
+        )?;
+        if let Some(segment) = Self::segment(&self.previous()) {
+            parts.push(segment);
+        }
+
+        let mut parts = parts.into_iter();
+        let mut expr: Expr = parts.next().unwrap();
+        for p in parts {
+            expr = Expr::Binary(Rc::new(expr), plus.clone(), Rc::new(p));
+        }
+
+        Ok(expr)
+    }
+
+    fn segment(token: &Token) -> Option<Expr> {
+        match token.literal() {
+            Some(Value::Str(s)) if !s.is_empty() => {
+                Some(Expr::Literal(token.clone(), Value::Str(s.clone())))
+            }
+            _ => None,
+        }
+    }
+
+    fn nest(&mut self) -> Result<(), ParseError> {
+        // Every enclosing construct would report its own missing end, so the
+        // rest of the source is skipped and only this error is kept.
+        if self.depth == NESTING_MAX {
+            let e: ParseError = ParseError::new("Too much nesting".to_string(), self.peek());
+            self.error(e.clone());
+            self.too_deep = true;
+            self.current = self.tokens.len() - 1;
+
+            return Err(e);
+        }
+        self.depth += 1;
+
+        Ok(())
+    }
+
+    // For right-associative operators, which parse their right operand by
+    // calling themselves.
+    fn nested(
+        &mut self,
+        parse: fn(&mut Parser) -> Result<Expr, ParseError>,
+    ) -> Result<Expr, ParseError> {
+        self.nest()?;
+        let res: Result<Expr, ParseError> = stack::guard(|| parse(self));
+        self.depth -= 1;
+
+        res
+    }
+
     fn sync(&mut self) {
         self.advance();
 
@@ -403,18 +811,23 @@ impl Parser {
             match self.peek().ttype() {
                 TokenType::Class => return,
                 TokenType::Fun => return,
+                TokenType::Import => return,
                 TokenType::Var => return,
                 TokenType::For => return,
                 TokenType::If => return,
                 TokenType::While => return,
                 TokenType::Print => return,
                 TokenType::Return => return,
+                TokenType::Throw => return,
+                TokenType::Try => return,
                 _ => self.advance(),
             };
         }
     }
 
-    fn show_error(&self, e: ParseError) {
-        println!("{}", e);
+    fn error(&mut self, e: ParseError) {
+        if !self.too_deep {
+            self.errors.push(e);
+        }
     }
 }
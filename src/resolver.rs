@@ -0,0 +1,345 @@
+use crate::error::ResolveError;
+use crate::expr::Expr;
+use crate::stack;
+use crate::stmt::Stmt;
+use crate::token::{Span, Token};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::mem;
+
+#[derive(Copy, Clone, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+pub struct Resolver {
+    scopes: Vec<HashMap<String, bool>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    loop_depth: usize,
+    errors: Vec<ResolveError>,
+}
+
+impl Resolver {
+    pub fn new() -> Resolver {
+        Resolver {
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            loop_depth: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn resolve(&mut self, statements: &[Stmt]) -> Result<(), Vec<ResolveError>> {
+        self.resolve_stmts(statements);
+
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(mem::take(&mut self.errors))
+        }
+    }
+
+    fn resolve_stmts(&mut self, statements: &[Stmt]) {
+        for s in statements {
+            self.resolve_stmt(s);
+        }
+    }
+
+    fn resolve_stmt(&mut self, s: &Stmt) {
+        stack::guard(|| match *s {
+            Stmt::Block(ref statements) => {
+                self.begin_scope();
+                self.resolve_stmts(statements);
+                self.end_scope();
+            }
+            Stmt::Break(ref keyword) => {
+                if self.loop_depth == 0 {
+                    self.error("Can't use 'break' outside of a loop", keyword);
+                }
+            }
+            Stmt::Class(ref name, ref superclass, ref methods) => {
+                let enclosing_class: ClassType = self.current_class;
+                self.current_class = ClassType::Class;
+
+                self.declare(name);
+                self.define(name);
+
+                if let Some(ref expr @ Expr::Var(ref super_name, _)) = *superclass {
+                    if super_name.lexeme() == name.lexeme() {
+                        self.error("A class can't inherit from itself", super_name);
+                    }
+
+                    self.current_class = ClassType::Subclass;
+                    self.resolve_expr(expr);
+
+                    self.begin_scope();
+                    self.put("super");
+                }
+
+                self.begin_scope();
+                self.put("this");
+
+                for m in methods {
+                    if let Stmt::Function(ref m_name, ref params, ref body) = *m {
+                        let ftype: FunctionType = if m_name.lexeme() == "init" {
+                            FunctionType::Initializer
+                        } else {
+                            FunctionType::Method
+                        };
+
+                        self.resolve_function(params, body, ftype);
+                    }
+                }
+
+                self.end_scope();
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+
+                self.current_class = enclosing_class;
+            }
+            Stmt::Continue(ref keyword) => {
+                if self.loop_depth == 0 {
+                    self.error("Can't use 'continue' outside of a loop", keyword);
+                }
+            }
+            Stmt::Expr(ref e) => self.resolve_expr(e),
+            Stmt::Function(ref name, ref params, ref body) => {
+                self.declare(name);
+                self.define(name);
+
+                self.resolve_function(params, body, FunctionType::Function);
+            }
+            Stmt::If(ref cond, ref then_s, ref else_s) => {
+                self.resolve_expr(cond);
+                self.resolve_stmt(then_s);
+                if let Some(else_s) = else_s {
+                    self.resolve_stmt(else_s);
+                }
+            }
+            Stmt::Import(_, ref name) => {
+                self.declare(name);
+                self.define(name);
+            }
+            Stmt::Print(ref e) => self.resolve_expr(e),
+            Stmt::Return(ref keyword, ref value) => {
+                if self.current_function == FunctionType::None {
+                    let span: Span = s.span().unwrap();
+                    self.error_spanning("Can't return from top-level code", keyword, span);
+                }
+
+                if let Some(v) = value {
+                    if self.current_function == FunctionType::Initializer {
+                        let msg: &str = "Can't return a value from an initializer";
+                        self.error_spanning(msg, keyword, v.span());
+                    }
+
+                    self.resolve_expr(v);
+                }
+            }
+            Stmt::Throw(_, ref value) => self.resolve_expr(value),
+            Stmt::Try(ref body, ref catch, ref finally) => {
+                self.begin_scope();
+                self.resolve_stmts(body);
+                self.end_scope();
+
+                if let Some((ref name, ref handler)) = *catch {
+                    self.begin_scope();
+                    self.declare(name);
+                    self.define(name);
+                    self.resolve_stmts(handler);
+                    self.end_scope();
+                }
+
+                if let Some(ref finally) = *finally {
+                    self.begin_scope();
+                    self.resolve_stmts(finally);
+                    self.end_scope();
+                }
+            }
+            Stmt::Var(ref name, ref init) => {
+                self.declare(name);
+                if let Some(i) = init {
+                    self.resolve_expr(i);
+                }
+                self.define(name);
+            }
+            Stmt::While(ref condition, ref body, ref increment) => {
+                self.resolve_expr(condition);
+                self.loop_depth += 1;
+                self.resolve_stmt(body);
+                self.loop_depth -= 1;
+                if let Some(inc) = increment {
+                    self.resolve_expr(inc);
+                }
+            }
+        })
+    }
+
+    fn resolve_function(&mut self, params: &[Token], body: &[Stmt], ftype: FunctionType) {
+        let enclosing_function: FunctionType = self.current_function;
+        let enclosing_loop_depth: usize = mem::take(&mut self.loop_depth);
+        self.current_function = ftype;
+
+        self.begin_scope();
+        for p in params {
+            self.declare(p);
+            self.define(p);
+        }
+        self.resolve_stmts(body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+        self.loop_depth = enclosing_loop_depth;
+    }
+
+    fn resolve_expr(&mut self, e: &Expr) {
+        stack::guard(|| match *e {
+            Expr::Assign(ref name, ref value, ref depth) => {
+                self.resolve_expr(value);
+                self.resolve_local(name, depth);
+            }
+            Expr::Binary(ref lhs, _, ref rhs) | Expr::Logical(ref lhs, _, ref rhs) => {
+                self.resolve_expr(lhs);
+                self.resolve_expr(rhs);
+            }
+            Expr::Call(ref callee, _, ref args) => {
+                self.resolve_expr(callee);
+                for a in args {
+                    self.resolve_expr(a);
+                }
+            }
+            Expr::Compound(ref target, _, ref value) => {
+                self.resolve_expr(value);
+                self.resolve_expr(target);
+            }
+            Expr::Conditional(ref cond, ref then_e, ref else_e) => {
+                self.resolve_expr(cond);
+                self.resolve_expr(then_e);
+                self.resolve_expr(else_e);
+            }
+            Expr::Get(ref object, _) => self.resolve_expr(object),
+            Expr::Grouping(ref expr) => self.resolve_expr(expr),
+            Expr::Index(ref object, _, ref index) => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+            }
+            Expr::List(_, ref elements) => {
+                for e in elements {
+                    self.resolve_expr(e);
+                }
+            }
+            Expr::Literal(..) => (),
+            Expr::Map(_, ref entries) => {
+                for (k, v) in entries {
+                    self.resolve_expr(k);
+                    self.resolve_expr(v);
+                }
+            }
+            Expr::Postfix(ref target, _) => self.resolve_expr(target),
+            Expr::Set(ref object, _, ref value) => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+            }
+            Expr::SetIndex(ref object, _, ref index, ref value) => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+                self.resolve_expr(value);
+            }
+            Expr::Stringify(ref expr) => self.resolve_expr(expr),
+            Expr::Super(ref keyword, _, ref depth) => {
+                match self.current_class {
+                    ClassType::None => self.error("Can't use 'super' outside of a class", keyword),
+                    ClassType::Class => {
+                        self.error("Can't use 'super' in a class with no superclass", keyword)
+                    }
+                    ClassType::Subclass => (),
+                }
+
+                self.resolve_local(keyword, depth);
+            }
+            Expr::This(ref keyword, ref depth) => {
+                if self.current_class == ClassType::None {
+                    self.error("Can't use 'this' outside of a class", keyword);
+                    return;
+                }
+
+                self.resolve_local(keyword, depth);
+            }
+            Expr::Unary(_, ref expr) => self.resolve_expr(expr),
+            Expr::Var(ref name, ref depth) => {
+                if let Some(scope) = self.scopes.last() {
+                    if scope.get(name.lexeme()) == Some(&false) {
+                        self.error("Can't read local variable in its own initializer", name);
+                    }
+                }
+
+                self.resolve_local(name, depth);
+            }
+        })
+    }
+
+    fn resolve_local(&mut self, name: &Token, depth: &Cell<Option<usize>>) {
+        for (i, scope) in self.scopes.iter().rev().enumerate() {
+            if scope.contains_key(name.lexeme()) {
+                depth.set(Some(i));
+                return;
+            }
+        }
+
+        depth.set(None);
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let redeclared: bool = match self.scopes.last_mut() {
+            Some(scope) => scope.insert(name.lexeme().to_string(), false).is_some(),
+            None => false,
+        };
+
+        if redeclared {
+            self.error("Already a variable with this name in this scope", name);
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme().to_string(), true);
+        }
+    }
+
+    fn put(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), true);
+        }
+    }
+
+    fn error(&mut self, msg: &str, token: &Token) {
+        self.errors
+            .push(ResolveError::new(msg.to_string(), token.clone()));
+    }
+
+    fn error_spanning(&mut self, msg: &str, token: &Token, span: Span) {
+        self.errors
+            .push(ResolveError::new(msg.to_string(), token.clone()).with_span(span));
+    }
+}
@@ -1,39 +1,85 @@
-use crate::error::error;
+use crate::error::ScanError;
 use crate::keyword::KEYWORDS;
-use crate::token::{Token, TokenType};
-use std::any::Any;
+use crate::token::{Source, Span, Token, TokenType};
+use crate::value::Value;
 use std::rc::Rc;
+use std::sync::Arc;
+use unicode_ident::{is_xid_continue, is_xid_start};
 
 pub struct Scanner {
-    orig_src: String,
+    shared_src: Arc<Source>,
     src: Vec<char>,
+    offsets: Vec<usize>,
     tokens: Vec<Token>,
     start: usize,
+    start_column: usize,
     current: usize,
     line: usize,
+    line_start: usize,
+    interpolations: Vec<Interpolation>,
+    errors: Vec<ScanError>,
+}
+
+// An interpolation whose closing `}` hasn't been reached yet, with where its
+// `${` is and how many braces are open inside it.
+struct Interpolation {
+    line: usize,
+    column: usize,
+    offset: usize,
+    depth: usize,
 }
 
 impl Scanner {
-    pub fn new(src: String) -> Scanner {
+    pub fn new(src: String, name: Option<String>) -> Scanner {
         Scanner {
-            orig_src: src.clone(),
             src: src.chars().collect(),
+            offsets: src
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain([src.len()])
+                .collect(),
+            shared_src: Arc::new(Source { name, text: src }),
             tokens: Vec::<Token>::new(),
             start: 0,
+            start_column: 1,
             current: 0,
             line: 1,
+            line_start: 0,
+            interpolations: Vec::new(),
+            errors: Vec::new(),
         }
     }
 
-    pub fn scan_tokens(&mut self) -> &Vec<Token> {
+    // Scanning carries on past an error so that all of them get reported.
+    pub fn scan_tokens(&mut self) -> Result<&Vec<Token>, Vec<ScanError>> {
+        // A `#!` first line lets scripts be made executable on Unix.
+        if self.src.starts_with(&['#', '!']) {
+            while self.peek() != '\n' && !self.is_at_end() {
+                self.advance();
+            }
+        }
+
         while !self.is_at_end() {
             self.start = self.current;
+            self.start_column = self.column(self.start);
             self.scan_token();
         }
+        if let Some(open) = self.interpolations.pop() {
+            self.unterminated(open);
+        }
 
-        self.tokens
-            .push(Token::new(TokenType::Eof, "".to_string(), None, self.line));
-        &self.tokens
+        let eof: Token = Token::new(TokenType::Eof, "".to_string(), None, self.line).at(
+            self.span(self.current, self.current),
+            self.column(self.current),
+            self.shared_src.clone(),
+        );
+        self.tokens.push(eof);
+
+        if self.errors.is_empty() {
+            Ok(&self.tokens)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
     }
 
     fn is_at_end(&self) -> bool {
@@ -46,13 +92,29 @@ impl Scanner {
         self.src[self.current - 1]
     }
 
-    fn add_token(&mut self, ttype: TokenType, literal: Option<Rc<dyn Any>>) {
-        self.tokens.push(Token::new(
+    fn add_token(&mut self, ttype: TokenType, literal: Option<Value>) {
+        let token: Token = Token::new(
             ttype,
-            self.orig_src[self.start..self.current].to_string(),
+            self.text(self.start, self.current).to_string(),
             literal,
             self.line,
-        ))
+        )
+        .at(
+            self.span(self.start, self.current),
+            self.start_column,
+            self.shared_src.clone(),
+        );
+
+        self.tokens.push(token)
+    }
+
+    // `start` and `end` index chars, so go through the byte offsets to slice.
+    fn text(&self, start: usize, end: usize) -> &str {
+        &self.shared_src.text[self.offsets[start]..self.offsets[end]]
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(self.offsets[start], self.offsets[end])
     }
 
     fn peek(&self) -> char {
@@ -71,6 +133,32 @@ impl Scanner {
         self.src[self.current + 1]
     }
 
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
+    fn column(&self, offset: usize) -> usize {
+        offset - self.line_start + 1
+    }
+
+    fn error(&mut self, offset: usize, message: String) {
+        self.error_at(self.line, self.column(offset), offset, message);
+    }
+
+    // Reports from `offset` up to where scanning currently is.
+    fn error_at(&mut self, line: usize, column: usize, offset: usize, message: String) {
+        let span: Span = self.span(offset, self.current);
+
+        self.errors.push(ScanError::new(
+            message,
+            line,
+            column,
+            span,
+            self.shared_src.clone(),
+        ));
+    }
+
     fn match_char(&mut self, expected: char) -> bool {
         let actual = self.peek();
         if actual != expected {
@@ -81,26 +169,141 @@ impl Scanner {
         true
     }
 
-    fn string(&mut self) {
+    // Scans string contents up to the closing quote, or up to a `${` that
+    // starts an interpolation. In the latter case the segment is emitted as an
+    // `Interpolation` token and scanning resumes here at the matching `}`,
+    // where the rest of the string ends in an `InterpolationEnd` token.
+    fn string(&mut self, end: TokenType) {
+        let (line, column): (usize, usize) = (self.line, self.column(self.start));
+        let mut value: String = String::new();
+
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1
-            };
-            self.advance();
+            if self.peek() == '$' && self.peek_next() == '{' {
+                self.interpolations.push(Interpolation {
+                    line: self.line,
+                    column: self.column(self.current),
+                    offset: self.current,
+                    depth: 0,
+                });
+                self.current += 2;
+                self.add_token(TokenType::Interpolation, Some(Value::Str(Rc::from(value))));
+                return;
+            }
+
+            let c: char = self.advance();
+            match c {
+                '\n' => {
+                    self.new_line();
+                    value.push(c);
+                }
+                '\\' if !self.is_at_end() => self.escape(&mut value),
+                _ => value.push(c),
+            }
         }
 
+        // A string left open inside an interpolation most likely means the
+        // interpolation's `}` is missing.
         if self.is_at_end() {
-            error(self.line, "Unterminated string".to_string());
+            match self.interpolations.pop() {
+                Some(open) => self.unterminated(open),
+                None => self.error_at(line, column, self.start, "Unterminated string".to_string()),
+            }
             return;
         }
 
         self.advance();
-        self.add_token(
-            TokenType::String,
-            Some(Rc::new(
-                self.orig_src[self.start + 1..self.current - 1].to_string(),
-            )),
-        );
+        self.add_token(end, Some(Value::Str(Rc::from(value))));
+    }
+
+    // Only the innermost interpolation left open is reported, since those
+    // around it are unterminated because of it.
+    fn unterminated(&mut self, open: Interpolation) {
+        self.interpolations.clear();
+        self.errors.push(ScanError::new(
+            "Unterminated interpolation".to_string(),
+            open.line,
+            open.column,
+            self.span(open.offset, open.offset + 2),
+            self.shared_src.clone(),
+        ));
+    }
+
+    fn escape(&mut self, value: &mut String) {
+        let backslash: usize = self.current - 1;
+
+        let c: char = match self.advance() {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '$' => '$',
+            'u' => match self.unicode_escape() {
+                Ok(c) => c,
+                Err(msg) => {
+                    self.error(backslash, msg.to_string());
+                    return;
+                }
+            },
+            c => {
+                self.error(backslash, format!("Invalid escape sequence '\\{}'", c));
+                if c == '\n' {
+                    self.new_line();
+                }
+                return;
+            }
+        };
+
+        value.push(c);
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, &'static str> {
+        if !self.match_char('{') {
+            return Err("Expect '{' after '\\u'");
+        }
+
+        let digits_start: usize = self.current;
+        while self.peek().is_ascii_hexdigit() {
+            self.advance();
+        }
+        let digits: String = self.text(digits_start, self.current).to_string();
+
+        if !self.match_char('}') {
+            return Err("Unterminated unicode escape");
+        }
+        if digits.is_empty() || digits.len() > 6 {
+            return Err("Unicode escape must have 1 to 6 hex digits");
+        }
+
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or("Invalid unicode code point")
+    }
+
+    fn block_comment(&mut self) {
+        let (line, column): (usize, usize) = (self.line, self.column(self.start));
+        let mut depth: usize = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                self.error_at(
+                    line,
+                    column,
+                    self.start,
+                    "Unterminated block comment".to_string(),
+                );
+                return;
+            }
+
+            match self.advance() {
+                '/' if self.match_char('*') => depth += 1,
+                '*' if self.match_char('/') => depth -= 1,
+                '\n' => self.new_line(),
+                _ => (),
+            }
+        }
     }
 
     fn number(&mut self) {
@@ -116,16 +319,16 @@ impl Scanner {
             }
         }
 
-        let n: f64 = self.orig_src[self.start..self.current].parse().unwrap();
-        self.add_token(TokenType::Number, Some(Rc::new(n)));
+        let n: f64 = self.text(self.start, self.current).parse().unwrap();
+        self.add_token(TokenType::Number, Some(Value::Number(n)));
     }
 
     fn identifier(&mut self) {
-        while self.peek().is_alphanumeric() {
+        while is_identifier_continue(self.peek()) {
             self.advance();
         }
 
-        let text: String = self.orig_src[self.start..self.current].to_string();
+        let text: String = self.text(self.start, self.current).to_string();
 
         let ttype = if KEYWORDS.contains_key(&text) {
             KEYWORDS[&text]
@@ -142,14 +345,68 @@ impl Scanner {
         match c {
             '(' => self.add_token(TokenType::LeftParen, None),
             ')' => self.add_token(TokenType::RightParen, None),
-            '{' => self.add_token(TokenType::LeftBrace, None),
-            '}' => self.add_token(TokenType::RightBrace, None),
+            '{' => {
+                if let Some(open) = self.interpolations.last_mut() {
+                    open.depth += 1;
+                }
+                self.add_token(TokenType::LeftBrace, None)
+            }
+            '}' => match self.interpolations.last_mut() {
+                Some(open) if open.depth == 0 => {
+                    self.interpolations.pop();
+                    self.string(TokenType::InterpolationEnd);
+                }
+                Some(open) => {
+                    open.depth -= 1;
+                    self.add_token(TokenType::RightBrace, None)
+                }
+                None => self.add_token(TokenType::RightBrace, None),
+            },
+            '[' => self.add_token(TokenType::LeftBracket, None),
+            ']' => self.add_token(TokenType::RightBracket, None),
+            ':' => self.add_token(TokenType::Colon, None),
+            '?' => self.add_token(TokenType::Question, None),
             ',' => self.add_token(TokenType::Comma, None),
             '.' => self.add_token(TokenType::Dot, None),
-            '-' => self.add_token(TokenType::Minus, None),
-            '+' => self.add_token(TokenType::Plus, None),
+            '-' => {
+                let ttype = if self.match_char('-') {
+                    TokenType::MinusMinus
+                } else if self.match_char('=') {
+                    TokenType::MinusEqual
+                } else {
+                    TokenType::Minus
+                };
+                self.add_token(ttype, None)
+            }
+            '+' => {
+                let ttype = if self.match_char('+') {
+                    TokenType::PlusPlus
+                } else if self.match_char('=') {
+                    TokenType::PlusEqual
+                } else {
+                    TokenType::Plus
+                };
+                self.add_token(ttype, None)
+            }
             ';' => self.add_token(TokenType::Semicolon, None),
-            '*' => self.add_token(TokenType::Star, None),
+            '%' => self.add_token(TokenType::Percent, None),
+            '*' => {
+                let ttype = if self.match_char('*') {
+                    TokenType::StarStar
+                } else if self.match_char('=') {
+                    TokenType::StarEqual
+                } else {
+                    TokenType::Star
+                };
+                self.add_token(ttype, None)
+            }
+            '~' => {
+                if self.match_char('/') {
+                    self.add_token(TokenType::TildeSlash, None);
+                } else {
+                    self.error(self.start, "Unexpected character".to_string());
+                }
+            }
             '!' => {
                 let ttype = if self.match_char('=') {
                     TokenType::BangEqual
@@ -187,22 +444,35 @@ impl Scanner {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                } else if self.match_char('*') {
+                    self.block_comment();
+                } else if self.match_char('=') {
+                    self.add_token(TokenType::SlashEqual, None);
                 } else {
                     self.add_token(TokenType::Slash, None);
                 };
             }
             ' ' | '\r' | '\t' => (),
-            '\n' => self.line += 1,
-            '"' => self.string(),
+            '\n' => self.new_line(),
+            '"' => self.string(TokenType::String),
             _ => {
                 if c.is_ascii_digit() {
                     self.number();
-                } else if c.is_alphabetic() {
+                } else if is_identifier_start(c) {
                     self.identifier();
                 } else {
-                    error(self.line, "Unexpected character".to_string());
+                    self.error(self.start, "Unexpected character".to_string());
                 }
             }
         }
     }
 }
+
+// Identifiers follow Unicode's XID rules, as Rust's own do, plus `_`.
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || is_xid_start(c)
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    is_xid_continue(c)
+}
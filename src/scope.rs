@@ -1,60 +1,108 @@
 use crate::environment::Environment;
-use crate::error::RuntimeError;
-use crate::literal::Literal;
+use crate::error::Exception;
 use crate::token::Token;
+use crate::value::Value;
+use std::cell::RefCell;
 use std::rc::Rc;
 
 pub struct Scope {
-    envs: Vec<Environment>,
+    globals: Rc<RefCell<Environment>>,
+    env: Rc<RefCell<Environment>>,
+}
+
+// The environments a call replaced, for `exit_call` to put back.
+pub struct Saved {
+    globals: Rc<RefCell<Environment>>,
+    env: Rc<RefCell<Environment>>,
 }
 
 impl Scope {
     pub fn new() -> Scope {
-        let mut s = Scope { envs: Vec::new() };
-        s.envs.insert(0, Environment::new());
+        let globals: Rc<RefCell<Environment>> = Rc::new(RefCell::new(Environment::new()));
 
-        s
+        Scope {
+            globals: globals.clone(),
+            env: globals,
+        }
     }
 
     pub fn wrap(&mut self) {
-        self.envs.insert(0, Environment::new());
+        self.env = Rc::new(RefCell::new(Environment::with_enclosing(self.env.clone())));
     }
 
     pub fn unwrap(&mut self) {
-        self.envs.remove(0);
+        let enclosing: Rc<RefCell<Environment>> = self.env.borrow().enclosing().unwrap();
+
+        self.env = enclosing;
     }
 
-    pub fn define(&mut self, name: String, value: Option<Rc<dyn Literal>>) {
-        self.envs.first_mut().unwrap().define(name, value);
+    pub fn current(&self) -> Rc<RefCell<Environment>> {
+        self.env.clone()
     }
 
-    pub fn get(&self, name: Token) -> Result<&Option<Rc<dyn Literal>>, RuntimeError> {
-        for e in self.envs.iter() {
-            if let Some(val) = e.get(name.clone()) {
-                return Ok(val);
-            }
+    // Functions run against the globals of the module they were declared in,
+    // which a module's own top level does too.
+    pub fn enter_call(
+        &mut self,
+        env: Rc<RefCell<Environment>>,
+        globals: Rc<RefCell<Environment>>,
+    ) -> Saved {
+        Saved {
+            globals: std::mem::replace(&mut self.globals, globals),
+            env: std::mem::replace(&mut self.env, env),
         }
+    }
+
+    pub fn exit_call(&mut self, saved: Saved) {
+        self.globals = saved.globals;
+        self.env = saved.env;
+    }
 
-        Err(RuntimeError::new(
-            format!("Undefined var '{}'", name.lexeme()),
-            name,
-        ))
+    pub fn globals(&self) -> Rc<RefCell<Environment>> {
+        self.globals.clone()
+    }
+
+    pub fn define(&mut self, name: String, value: Value) {
+        self.env.borrow_mut().define(name, value);
+    }
+
+    pub fn get(&self, name: Token, depth: Option<usize>) -> Result<Value, Exception> {
+        self.lookup(depth)
+            .borrow()
+            .get(name.lexeme())
+            .ok_or(Exception::new(
+                format!("Undefined var '{}'", name.lexeme()),
+                name,
+            ))
     }
 
     pub fn assign(
         &mut self,
         name: Token,
-        value: Option<Rc<dyn Literal>>,
-    ) -> Result<(), RuntimeError> {
-        for e in self.envs.iter_mut() {
-            if e.assign(name.clone(), value.clone()).is_some() {
-                return Ok(());
-            }
+        depth: Option<usize>,
+        value: Value,
+    ) -> Result<(), Exception> {
+        self.lookup(depth)
+            .borrow_mut()
+            .assign(name.lexeme(), value)
+            .ok_or(Exception::new(
+                format!("Undefined var '{}'", name.lexeme()),
+                name,
+            ))
+    }
+
+    fn lookup(&self, depth: Option<usize>) -> Rc<RefCell<Environment>> {
+        let distance: usize = match depth {
+            Some(d) => d,
+            None => return self.globals.clone(),
+        };
+
+        let mut env: Rc<RefCell<Environment>> = self.env.clone();
+        for _ in 0..distance {
+            let enclosing: Rc<RefCell<Environment>> = env.borrow().enclosing().unwrap();
+            env = enclosing;
         }
 
-        Err(RuntimeError::new(
-            format!("Undefined var '{}'", name.lexeme()),
-            name,
-        ))
+        env
     }
 }
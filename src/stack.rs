@@ -0,0 +1,13 @@
+// The parser, resolver, compiler and tree-walker recurse on the native stack
+// as deep as a script nests. Each recursive step goes through `guard`, which
+// moves onto a fresh heap-allocated segment when the current stack runs low,
+// so no script can overflow the stack of the thread running it.
+
+// Room for the deepest chain of frames between two guarded calls, even in
+// debug builds.
+const RED_ZONE: usize = 256 * 1024;
+const SEGMENT_SIZE: usize = 4 * 1024 * 1024;
+
+pub fn guard<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(RED_ZONE, SEGMENT_SIZE, f)
+}
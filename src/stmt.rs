@@ -1,12 +1,135 @@
 use crate::expr::Expr;
-use crate::token::Token;
+use crate::token::{Span, Token};
+use std::mem;
 use std::rc::Rc;
 
 pub enum Stmt {
+    Break(Token),
+    Class(Token, Option<Expr>, Vec<Stmt>),
+    Continue(Token),
     Expr(Expr),
+    Function(Token, Vec<Token>, Rc<Vec<Stmt>>),
     If(Expr, Rc<Stmt>, Option<Rc<Stmt>>),
+    Import(Token, Token),
     Print(Expr),
+    Return(Token, Option<Expr>),
+    Throw(Token, Expr),
+    Try(Vec<Stmt>, Option<(Token, Vec<Stmt>)>, Option<Rc<Vec<Stmt>>>),
     Var(Token, Option<Expr>),
     Block(Vec<Stmt>),
-    While(Expr, Rc<Stmt>),
+    While(Expr, Rc<Stmt>, Option<Expr>),
+}
+
+impl Stmt {
+    // Like `Expr::span`, but `None` for statements with nothing in them to
+    // point at, such as an empty block.
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            Stmt::Break(ref keyword) | Stmt::Continue(ref keyword) => Some(keyword.span()),
+            Stmt::Class(ref name, ref superclass, ref methods) => join(
+                [Some(name.span()), superclass.as_ref().map(Expr::span)]
+                    .into_iter()
+                    .chain(methods.iter().map(Stmt::span)),
+            ),
+            Stmt::Expr(ref e) | Stmt::Print(ref e) => Some(e.span()),
+            Stmt::Function(ref name, _, ref body) => join(
+                [Some(name.span())]
+                    .into_iter()
+                    .chain(body.iter().map(Stmt::span)),
+            ),
+            Stmt::If(ref cond, ref then_s, ref else_s) => join([
+                Some(cond.span()),
+                then_s.span(),
+                else_s.as_ref().and_then(|s| s.span()),
+            ]),
+            Stmt::Import(ref path, ref name) => Some(path.span().to(name.span())),
+            Stmt::Return(ref keyword, ref value) => {
+                join([Some(keyword.span()), value.as_ref().map(Expr::span)])
+            }
+            Stmt::Throw(ref keyword, ref value) => Some(keyword.span().to(value.span())),
+            Stmt::Try(ref body, ref catch, ref finally) => join(
+                body.iter()
+                    .map(Stmt::span)
+                    .chain(catch.iter().flat_map(|(name, handler)| {
+                        [Some(name.span())]
+                            .into_iter()
+                            .chain(handler.iter().map(Stmt::span))
+                    }))
+                    .chain(finally.iter().flat_map(|f| f.iter().map(Stmt::span))),
+            ),
+            Stmt::Var(ref name, ref init) => {
+                join([Some(name.span()), init.as_ref().map(Expr::span)])
+            }
+            Stmt::Block(ref statements) => join(statements.iter().map(Stmt::span)),
+            Stmt::While(ref cond, ref body, ref increment) => join([
+                Some(cond.span()),
+                body.span(),
+                increment.as_ref().map(Expr::span),
+            ]),
+        }
+    }
+}
+
+fn join(spans: impl IntoIterator<Item = Option<Span>>) -> Option<Span> {
+    spans.into_iter().flatten().reduce(Span::to)
+}
+
+// Like `Expr`, statements are freed through a worklist, so deeply nested
+// blocks don't recurse.
+impl Drop for Stmt {
+    fn drop(&mut self) {
+        let mut work: Vec<Stmt> = Vec::new();
+        self.take_children(&mut work);
+        while let Some(mut s) = work.pop() {
+            s.take_children(&mut work);
+        }
+    }
+}
+
+impl Stmt {
+    fn take_children(&mut self, work: &mut Vec<Stmt>) {
+        match *self {
+            Stmt::Block(ref mut statements) | Stmt::Class(_, _, ref mut statements) => {
+                work.append(statements)
+            }
+            Stmt::Function(_, _, ref mut body) => take_body(body, work),
+            Stmt::If(_, ref mut then_s, ref mut else_s) => {
+                take(then_s, work);
+                if let Some(ref mut else_s) = *else_s {
+                    take(else_s, work);
+                }
+            }
+            Stmt::Try(ref mut body, ref mut catch, ref mut finally) => {
+                work.append(body);
+                if let Some((_, ref mut catch)) = *catch {
+                    work.append(catch);
+                }
+                if let Some(ref mut finally) = *finally {
+                    take_body(finally, work);
+                }
+            }
+            Stmt::While(_, ref mut body, _) => take(body, work),
+            Stmt::Break(_)
+            | Stmt::Continue(_)
+            | Stmt::Expr(_)
+            | Stmt::Import(..)
+            | Stmt::Print(_)
+            | Stmt::Return(..)
+            | Stmt::Throw(..)
+            | Stmt::Var(..) => (),
+        }
+    }
+}
+
+// Bodies shared with a function value are left for it to free.
+fn take(s: &mut Rc<Stmt>, work: &mut Vec<Stmt>) {
+    if let Some(s) = Rc::get_mut(s) {
+        work.push(mem::replace(s, Stmt::Block(Vec::new())));
+    }
+}
+
+fn take_body(body: &mut Rc<Vec<Stmt>>, work: &mut Vec<Stmt>) {
+    if let Some(body) = Rc::get_mut(body) {
+        work.append(body);
+    }
 }
@@ -1,5 +1,6 @@
-use std::any::Any;
+use crate::value::Value;
 use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum TokenType {
@@ -8,11 +9,16 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Semicolon,
     Minus,
     Plus,
+    Percent,
+    Question,
     Slash,
     Star,
 
@@ -25,57 +31,133 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    MinusEqual,
+    MinusMinus,
+    PlusEqual,
+    PlusPlus,
+    SlashEqual,
+    StarEqual,
+    StarStar,
+    TildeSlash,
 
     // Literals.
     Identifier,
     String,
+    Interpolation,
+    InterpolationEnd,
     Number,
 
     // Keywords.
     And,
+    As,
+    Break,
+    Catch,
     Class,
+    Continue,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Import,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
     Eof,
 }
 
+// Byte offsets into the source, `end` being exclusive. They are kept small
+// since every token carries one.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Span {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span {
+            start: start as u32,
+            end: end as u32,
+        }
+    }
+
+    pub fn range(self) -> std::ops::Range<usize> {
+        self.start as usize..self.end as usize
+    }
+
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+// The text tokens are scanned from. Modules are named after their file so
+// that errors in them say where they are; the main script has no name.
+#[derive(Debug, Default)]
+pub struct Source {
+    pub name: Option<String>,
+    pub text: String,
+}
+
+// Tokens keep a handle on the source they were scanned from, so an error can
+// show where it happened even when it is reported from another module.
 #[derive(Clone, Debug)]
 pub struct Token {
     ttype: TokenType,
-    lexeme: String,
-    literal: Option<Rc<dyn Any>>,
-    line: usize,
+    lexeme: Rc<str>,
+    literal: Option<Value>,
+    line: u32,
+    column: u32,
+    span: Span,
+    src: Arc<Source>,
 }
 
 impl Token {
-    pub fn new(
-        ttype: TokenType,
-        lexeme: String,
-        literal: Option<Rc<dyn Any>>,
-        line: usize,
-    ) -> Token {
+    pub fn new(ttype: TokenType, lexeme: String, literal: Option<Value>, line: usize) -> Token {
         Token {
             ttype,
-            lexeme,
+            lexeme: Rc::from(lexeme),
             literal,
-            line,
+            line: line as u32,
+            column: 0,
+            span: Span::default(),
+            src: Arc::default(),
+        }
+    }
+
+    pub fn at(self, span: Span, column: usize, src: Arc<Source>) -> Token {
+        Token {
+            span,
+            column: column as u32,
+            src,
+            ..self
+        }
+    }
+
+    // A token the parser or a backend makes up, located where `self` is.
+    pub fn synthetic(&self, ttype: TokenType, lexeme: &str) -> Token {
+        Token {
+            ttype,
+            lexeme: Rc::from(lexeme),
+            literal: None,
+            ..self.clone()
         }
     }
 
-    pub fn lexeme(&self) -> &String {
+    pub fn lexeme(&self) -> &str {
         &self.lexeme
     }
 
@@ -83,11 +165,24 @@ impl Token {
         self.ttype
     }
 
-    pub fn literal(&self) -> &Option<Rc<dyn Any>> {
+    pub fn literal(&self) -> &Option<Value> {
         &self.literal
     }
 
     pub fn line(&self) -> usize {
-        self.line
+        self.line as usize
+    }
+
+    // Counted in characters from the start of the line, starting at 1.
+    pub fn column(&self) -> usize {
+        self.column as usize
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn source(&self) -> Arc<Source> {
+        self.src.clone()
     }
 }
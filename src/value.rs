@@ -0,0 +1,526 @@
+use crate::chunk::Chunk;
+use crate::environment::Environment;
+use crate::stack;
+use crate::stmt::Stmt;
+use crate::token::Token;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+// Hosts read values through the accessors and conversions below, since most
+// variants wrap types only the backends can use.
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    Str(Rc<str>),
+    List(Rc<RefCell<List>>),
+    Map(Rc<RefCell<Map>>),
+    Native(Rc<Native>),
+    Function(Rc<Function>),
+    Closure(Rc<Closure>),
+    Class(Rc<Class>),
+    Instance(Rc<Instance>),
+    BoundMethod(Rc<BoundMethod>),
+    Module(Rc<Module>),
+}
+
+impl Value {
+    pub fn is_nil(&self) -> bool {
+        matches!(*self, Value::Nil)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<f64> {
+        match *self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::Str(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // A copy of the list's elements as they are now.
+    pub fn to_list(&self) -> Option<Vec<Value>> {
+        match *self {
+            Value::List(ref list) => Some(list.borrow().to_vec()),
+            _ => None,
+        }
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        !matches!(*self, Value::Nil | Value::Bool(false))
+    }
+
+    pub fn is_equal(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Number(l), Value::Number(r)) => l == r,
+            (Value::Str(l), Value::Str(r)) => l == r,
+            (Value::List(l), Value::List(r)) => Rc::ptr_eq(l, r),
+            (Value::Map(l), Value::Map(r)) => Rc::ptr_eq(l, r),
+            (Value::Native(l), Value::Native(r)) => Rc::ptr_eq(l, r),
+            (Value::Function(l), Value::Function(r)) => Rc::ptr_eq(l, r),
+            (Value::Closure(l), Value::Closure(r)) => Rc::ptr_eq(l, r),
+            (Value::Class(l), Value::Class(r)) => Rc::ptr_eq(l, r),
+            (Value::Instance(l), Value::Instance(r)) => Rc::ptr_eq(l, r),
+            (Value::BoundMethod(l), Value::BoundMethod(r)) => Rc::ptr_eq(l, r),
+            (Value::Module(l), Value::Module(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+impl Value {
+    // Lists and maps that are already being written further up are shown as
+    // `[...]` and `{...}`, so one that contains itself doesn't recurse forever.
+    fn write(&self, f: &mut fmt::Formatter, quoted: bool, open: &mut Vec<usize>) -> fmt::Result {
+        stack::guard(|| match *self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Str(ref s) if quoted => write!(f, "{:?}", s),
+            Value::Str(ref s) => write!(f, "{}", s),
+            Value::List(ref list) => {
+                let id: usize = Rc::as_ptr(list) as usize;
+                if open.contains(&id) {
+                    return write!(f, "[...]");
+                }
+
+                open.push(id);
+                write!(f, "[")?;
+                for (i, e) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    e.write(f, true, open)?;
+                }
+                open.pop();
+
+                write!(f, "]")
+            }
+            Value::Map(ref map) => {
+                let id: usize = Rc::as_ptr(map) as usize;
+                if open.contains(&id) {
+                    return write!(f, "{{...}}");
+                }
+
+                open.push(id);
+                write!(f, "{{")?;
+                for (i, (k, v)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    k.value().write(f, true, open)?;
+                    write!(f, ": ")?;
+                    v.write(f, true, open)?;
+                }
+                open.pop();
+
+                write!(f, "}}")
+            }
+            Value::Native(ref native) => write!(f, "<native fn {}>", native.name),
+            Value::Function(ref fun) => write!(f, "<fn {}>", fun.name.lexeme()),
+            Value::Closure(ref c) => write!(f, "{}", c.proto),
+            Value::Class(ref c) => write!(f, "{}", c.name),
+            Value::Instance(ref i) => write!(f, "{} instance", i.class.name),
+            Value::BoundMethod(ref m) => write!(f, "{}", m.method.proto),
+            Value::Module(ref m) => write!(f, "<module {}>", m.name),
+        })
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, false, &mut Vec::new())
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, true, &mut Vec::new())
+    }
+}
+
+#[derive(Clone)]
+pub struct Function {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Rc<Vec<Stmt>>,
+    pub closure: Rc<RefCell<Environment>>,
+    pub globals: Rc<RefCell<Environment>>,
+    pub is_initializer: bool,
+}
+
+impl Function {
+    pub fn bind(&self, instance: Value) -> Function {
+        let mut env: Environment = Environment::with_enclosing(self.closure.clone());
+        env.define("this".to_string(), instance);
+
+        Function {
+            closure: Rc::new(RefCell::new(env)),
+            ..self.clone()
+        }
+    }
+}
+
+// Only values compared structurally by `is_equal` can be keys, so that hashing
+// agrees with equality. NaN is rejected because it isn't equal to itself.
+#[derive(Clone)]
+pub struct Key(Value);
+
+impl Key {
+    pub fn new(value: Value) -> Option<Key> {
+        match value {
+            Value::Number(n) if n.is_nan() => None,
+            Value::Bool(_) | Value::Number(_) | Value::Str(_) => Some(Key(value)),
+            _ => None,
+        }
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.0
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Key) -> bool {
+        self.0.is_equal(&other.0)
+    }
+}
+
+impl Eq for Key {}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self.0 {
+            Value::Bool(b) => b.hash(state),
+            // Adding 0.0 turns -0.0 into 0.0, which it is equal to.
+            Value::Number(n) => (n + 0.0).to_bits().hash(state),
+            Value::Str(ref s) => s.hash(state),
+            _ => unreachable!(),
+        }
+    }
+}
+
+// The elements of a list value.
+#[derive(Default)]
+pub struct List(Vec<Value>);
+
+impl From<Vec<Value>> for List {
+    fn from(values: Vec<Value>) -> List {
+        List(values)
+    }
+}
+
+impl Deref for List {
+    type Target = Vec<Value>;
+
+    fn deref(&self) -> &Vec<Value> {
+        &self.0
+    }
+}
+
+impl DerefMut for List {
+    fn deref_mut(&mut self) -> &mut Vec<Value> {
+        &mut self.0
+    }
+}
+
+// Freeing a value frees what it holds, which would recurse once per level of
+// a deeply nested list or a long chain of instances. Containers move what
+// they hold onto a worklist instead, and only the last reference to a
+// container empties it there.
+fn free(values: impl IntoIterator<Item = Value>) {
+    let mut work: Vec<Value> = values.into_iter().filter(is_last).collect();
+
+    while let Some(value) = work.pop() {
+        match value {
+            Value::List(ref list) => work.extend(
+                mem::take(&mut list.borrow_mut().0)
+                    .into_iter()
+                    .filter(is_last),
+            ),
+            Value::Map(ref map) => work.extend(map.borrow_mut().take_values().filter(is_last)),
+            Value::Instance(ref instance) => work.extend(
+                instance
+                    .fields
+                    .borrow_mut()
+                    .drain()
+                    .map(|(_, v)| v)
+                    .filter(is_last),
+            ),
+            _ => unreachable!(),
+        }
+    }
+}
+
+// Whether `value` is the last reference to a container. Anything else is
+// freed without recursing.
+fn is_last(value: &Value) -> bool {
+    match *value {
+        Value::List(ref list) => Rc::strong_count(list) == 1,
+        Value::Map(ref map) => Rc::strong_count(map) == 1,
+        Value::Instance(ref instance) => Rc::strong_count(instance) == 1,
+        _ => false,
+    }
+}
+
+impl Drop for List {
+    fn drop(&mut self) {
+        free(mem::take(&mut self.0));
+    }
+}
+
+// Entries are kept in insertion order so iterating over keys is deterministic.
+pub struct Map {
+    indices: HashMap<Key, usize>,
+    entries: Vec<(Key, Value)>,
+}
+
+impl Map {
+    pub fn new() -> Map {
+        Map {
+            indices: HashMap::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, key: &Key) -> Option<&Value> {
+        self.indices.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn contains(&self, key: &Key) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: Key, value: Value) {
+        match self.indices.get(&key) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.indices.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &Key) -> Option<Value> {
+        let idx: usize = self.indices.remove(key)?;
+        let (_, value): (Key, Value) = self.entries.remove(idx);
+
+        for i in self.indices.values_mut() {
+            if *i > idx {
+                *i -= 1;
+            }
+        }
+
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Key, Value)> {
+        self.entries.iter()
+    }
+
+    fn take_values(&mut self) -> impl Iterator<Item = Value> {
+        self.indices.clear();
+        mem::take(&mut self.entries).into_iter().map(|(_, v)| v)
+    }
+}
+
+impl Drop for Map {
+    fn drop(&mut self) {
+        free(self.take_values());
+    }
+}
+
+pub type NativeFn = Box<dyn Fn(&[Value]) -> Result<Value, String>>;
+
+pub struct Native {
+    pub name: String,
+    pub arity: usize,
+    pub fun: NativeFn,
+}
+
+pub struct Proto {
+    pub name: String,
+    pub arity: usize,
+    pub upvalues: Vec<(bool, u8)>,
+    pub chunk: Chunk,
+}
+
+impl fmt::Display for Proto {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "<script>")
+        } else {
+            write!(f, "<fn {}>", self.name)
+        }
+    }
+}
+
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+pub struct Closure {
+    pub proto: Rc<Proto>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    pub globals: Rc<RefCell<Environment>>,
+}
+
+// Both backends copy inherited methods down into the subclass when it is
+// created, so method lookup never has to walk a superclass chain.
+pub struct Class {
+    pub name: String,
+    pub methods: RefCell<HashMap<String, Value>>,
+}
+
+impl Class {
+    pub fn find_method(&self, name: &str) -> Option<Value> {
+        self.methods.borrow().get(name).cloned()
+    }
+}
+
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: RefCell<HashMap<String, Value>>,
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        free(self.fields.get_mut().drain().map(|(_, v)| v));
+    }
+}
+
+pub struct BoundMethod {
+    pub receiver: Value,
+    pub method: Rc<Closure>,
+}
+
+// A module's top-level variables stay live, so later assignments made by its
+// own functions are visible through `m.name`.
+pub struct Module {
+    pub name: String,
+    pub globals: Rc<RefCell<Environment>>,
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Value {
+        Value::Number(n)
+    }
+}
+
+// Lox only has doubles, so integers past 2^53 lose precision.
+macro_rules! from_integer {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Value {
+                fn from(n: $t) -> Value {
+                    Value::Number(n as f64)
+                }
+            }
+        )*
+    };
+}
+
+from_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::Str(Rc::from(s))
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::Str(Rc::from(s))
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Value {
+        value.map_or(Value::Nil, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Value {
+        let values: Vec<Value> = values.into_iter().map(Into::into).collect();
+
+        Value::List(Rc::new(RefCell::new(List::from(values))))
+    }
+}
+
+// Failed conversions hand the original value back.
+impl TryFrom<Value> for f64 {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<f64, Value> {
+        match value {
+            Value::Number(n) => Ok(n),
+            _ => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<bool, Value> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            _ => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<String, Value> {
+        match value {
+            Value::Str(ref s) => Ok(s.to_string()),
+            _ => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vec<Value> {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Vec<Value>, Value> {
+        match value {
+            Value::List(ref list) => Ok(list.borrow().to_vec()),
+            _ => Err(value),
+        }
+    }
+}
@@ -0,0 +1,729 @@
+use crate::builtins;
+use crate::chunk::OpCode;
+use crate::compiler::Compiler;
+use crate::environment::Environment;
+use crate::error::{Error, Exception};
+use crate::module::{self, Modules};
+use crate::stmt::Stmt;
+use crate::token::Token;
+use crate::value::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+const FRAMES_MAX: usize = 1024;
+
+struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    slots: usize,
+}
+
+struct Handler {
+    frames: usize,
+    stack: usize,
+    target: usize,
+    catch: bool,
+}
+
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    globals: Rc<RefCell<Environment>>,
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    handlers: Vec<Handler>,
+    // Errors waiting for their `finally` block to finish, with the stack slot
+    // that block keeps them in. A block left early by `break`, `continue` or
+    // `return` leaves its entry behind until that slot is reused.
+    pending: Vec<(usize, Exception)>,
+    modules: Modules,
+    natives: Vec<Rc<Native>>,
+    out: Box<dyn Write>,
+    diagnostics: Box<dyn Write>,
+}
+
+impl Vm {
+    pub fn new() -> Vm {
+        let mut vm: Vm = Vm {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            globals: Rc::new(RefCell::new(Environment::new())),
+            open_upvalues: Vec::new(),
+            handlers: Vec::new(),
+            pending: Vec::new(),
+            modules: Modules::new(),
+            natives: Vec::new(),
+            out: Box::new(io::stdout()),
+            diagnostics: Box::new(io::stderr()),
+        };
+        for native in builtins::natives() {
+            vm.define_native(native);
+        }
+
+        vm
+    }
+
+    pub fn define_native(&mut self, native: Native) {
+        let native: Rc<Native> = Rc::new(native);
+
+        self.globals
+            .borrow_mut()
+            .define(native.name.clone(), Value::Native(native.clone()));
+        self.natives.push(native);
+    }
+
+    pub fn set_output(&mut self, out: Box<dyn Write>) {
+        self.out = out;
+    }
+
+    pub fn set_diagnostics(&mut self, diagnostics: Box<dyn Write>) {
+        self.diagnostics = diagnostics;
+    }
+
+    pub fn diagnostics(&mut self) -> &mut dyn Write {
+        &mut *self.diagnostics
+    }
+
+    pub fn set_path(&mut self, path: &Path) {
+        self.modules.set_main(path);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.globals.borrow().get(name)
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) {
+        self.globals.borrow_mut().define(name.to_string(), value);
+    }
+
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), Error> {
+        let proto: Rc<Proto> = Compiler::new()
+            .compile(statements)
+            .map_err(Error::Compile)?;
+        let closure: Rc<Closure> = Rc::new(Closure {
+            proto,
+            upvalues: Vec::new(),
+            globals: self.globals.clone(),
+        });
+
+        self.stack.push(Value::Closure(closure.clone()));
+        let res: Result<(), Exception> = self.call(closure, 0).and_then(|_| self.run(0));
+
+        // A failed run leaves its frames behind, and a `finally` block left
+        // early leaves its error pending even when the run succeeds.
+        self.stack.clear();
+        self.frames.clear();
+        self.open_upvalues.clear();
+        self.handlers.clear();
+        self.pending.clear();
+
+        res.map_err(Error::from)
+    }
+
+    // Runs until the frame count drops back to `base`. Handlers installed
+    // below `base` belong to an outer `run`, which the error is returned to.
+    fn run(&mut self, base: usize) -> Result<(), Exception> {
+        loop {
+            match self.execute(base) {
+                Ok(()) => return Ok(()),
+                Err(e) => self.catch(e, base)?,
+            }
+        }
+    }
+
+    // Unwinds to the innermost handler and resumes at its target, with either
+    // the caught value or a placeholder for the pending error on top of the
+    // stack.
+    fn catch(&mut self, e: Exception, base: usize) -> Result<(), Exception> {
+        let handler: Handler = match self.handlers.pop() {
+            Some(handler) if handler.frames > base => handler,
+            Some(handler) => {
+                self.handlers.push(handler);
+                return Err(e);
+            }
+            None => return Err(e),
+        };
+
+        self.frames.truncate(handler.frames);
+        self.close_upvalues(handler.stack);
+        self.stack.truncate(handler.stack);
+        self.frame_mut().ip = handler.target;
+
+        if handler.catch {
+            self.stack.push(builtins::catch(&e));
+        } else {
+            self.drop_pending(handler.stack);
+            self.pending.push((handler.stack, e));
+            self.stack.push(Value::Nil);
+        }
+
+        Ok(())
+    }
+
+    // Forgets the errors of `finally` blocks whose slot is at or above `slot`,
+    // which can only have been left early.
+    fn drop_pending(&mut self, slot: usize) {
+        while self.pending.last().is_some_and(|(s, _)| *s >= slot) {
+            self.pending.pop();
+        }
+    }
+
+    fn execute(&mut self, base: usize) -> Result<(), Exception> {
+        loop {
+            let frame: &mut CallFrame = self.frames.last_mut().unwrap();
+            let op: OpCode = frame.closure.proto.chunk.code(frame.ip);
+            frame.ip += 1;
+
+            match op {
+                OpCode::Constant(idx) => {
+                    let value: Value = self.frame().closure.proto.chunk.constant(idx).clone();
+                    self.stack.push(value);
+                }
+                OpCode::Nil => self.stack.push(Value::Nil),
+                OpCode::True => self.stack.push(Value::Bool(true)),
+                OpCode::False => self.stack.push(Value::Bool(false)),
+                OpCode::Pop => {
+                    self.stack.pop();
+                }
+                OpCode::Dup(n) => {
+                    let len: usize = self.stack.len();
+                    self.stack.extend_from_within(len - n as usize..);
+                }
+                OpCode::Bury(n) => {
+                    let value: Value = self.stack.pop().unwrap();
+                    let at: usize = self.stack.len() - n as usize;
+                    self.stack.insert(at, value);
+                }
+                OpCode::GetLocal(slot) => {
+                    let value: Value = self.stack[self.frame().slots + slot as usize].clone();
+                    self.stack.push(value);
+                }
+                OpCode::SetLocal(slot) => {
+                    let idx: usize = self.frame().slots + slot as usize;
+                    self.stack[idx] = self.peek(0).clone();
+                }
+                OpCode::GetGlobal(idx) => {
+                    let name: Rc<str> = self.read_string(idx);
+
+                    let value: Option<Value> = self.frame().closure.globals.borrow().get(&name);
+                    match value {
+                        Some(value) => self.stack.push(value),
+                        None => return Err(self.undefined_var(&name)),
+                    }
+                }
+                OpCode::DefineGlobal(idx) => {
+                    let name: Rc<str> = self.read_string(idx);
+                    let value: Value = self.stack.pop().unwrap();
+
+                    self.frame()
+                        .closure
+                        .globals
+                        .borrow_mut()
+                        .define(name.to_string(), value);
+                }
+                OpCode::SetGlobal(idx) => {
+                    let name: Rc<str> = self.read_string(idx);
+                    let value: Value = self.peek(0).clone();
+
+                    let assigned: Option<()> = self
+                        .frame()
+                        .closure
+                        .globals
+                        .borrow_mut()
+                        .assign(&name, value);
+                    if assigned.is_none() {
+                        return Err(self.undefined_var(&name));
+                    }
+                }
+                OpCode::GetUpvalue(idx) => {
+                    let value: Value = match *self.frame().closure.upvalues[idx as usize].borrow() {
+                        Upvalue::Open(slot) => self.stack[slot].clone(),
+                        Upvalue::Closed(ref value) => value.clone(),
+                    };
+                    self.stack.push(value);
+                }
+                OpCode::SetUpvalue(idx) => {
+                    let value: Value = self.peek(0).clone();
+                    let upvalue: Rc<RefCell<Upvalue>> =
+                        self.frame().closure.upvalues[idx as usize].clone();
+
+                    match *upvalue.borrow_mut() {
+                        Upvalue::Open(slot) => self.stack[slot] = value,
+                        Upvalue::Closed(ref mut closed) => *closed = value,
+                    };
+                }
+                OpCode::GetProperty(idx) => {
+                    let name: Rc<str> = self.read_string(idx);
+
+                    match self.peek(0).clone() {
+                        Value::Instance(instance) => {
+                            let field: Option<Value> =
+                                instance.fields.borrow().get(&*name).cloned();
+                            match field {
+                                Some(value) => {
+                                    self.stack.pop();
+                                    self.stack.push(value);
+                                }
+                                None => self.bind_method(&instance.class, &name)?,
+                            }
+                        }
+                        other => {
+                            let value: Value =
+                                builtins::property(&other, &name).map_err(|msg| self.error(msg))?;
+
+                            self.stack.pop();
+                            self.stack.push(value);
+                        }
+                    }
+                }
+                OpCode::SetProperty(idx) => {
+                    let name: Rc<str> = self.read_string(idx);
+
+                    let instance: Rc<Instance> = match *self.peek(1) {
+                        Value::Instance(ref instance) => instance.clone(),
+                        _ => return Err(self.error("Only instances have fields".to_string())),
+                    };
+
+                    let value: Value = self.stack.pop().unwrap();
+                    instance
+                        .fields
+                        .borrow_mut()
+                        .insert(name.to_string(), value.clone());
+
+                    self.stack.pop();
+                    self.stack.push(value);
+                }
+                OpCode::GetSuper(idx) => {
+                    let name: Rc<str> = self.read_string(idx);
+
+                    match self.stack.pop().unwrap() {
+                        Value::Class(superclass) => self.bind_method(&superclass, &name)?,
+                        _ => unreachable!(),
+                    }
+                }
+                OpCode::GetIndex => {
+                    let index: Value = self.stack.pop().unwrap();
+                    let object: Value = self.stack.pop().unwrap();
+
+                    let value: Value =
+                        builtins::get_index(&object, &index).map_err(|msg| self.error(msg))?;
+                    self.stack.push(value);
+                }
+                OpCode::SetIndex => {
+                    let value: Value = self.stack.pop().unwrap();
+                    let index: Value = self.stack.pop().unwrap();
+                    let object: Value = self.stack.pop().unwrap();
+
+                    builtins::set_index(&object, &index, value.clone())
+                        .map_err(|msg| self.error(msg))?;
+                    self.stack.push(value);
+                }
+                OpCode::List(count) => {
+                    let list: Vec<Value> = self.stack.split_off(self.stack.len() - count as usize);
+
+                    self.stack
+                        .push(Value::List(Rc::new(RefCell::new(List::from(list)))));
+                }
+                OpCode::Map(count) => {
+                    let entries: Vec<Value> =
+                        self.stack.split_off(self.stack.len() - 2 * count as usize);
+
+                    let mut map: Map = Map::new();
+                    for entry in entries.chunks(2) {
+                        let key: Key =
+                            builtins::map_key(&entry[0]).map_err(|msg| self.error(msg))?;
+                        map.insert(key, entry[1].clone());
+                    }
+                    self.stack.push(Value::Map(Rc::new(RefCell::new(map))));
+                }
+                OpCode::Equal => {
+                    let r: Value = self.stack.pop().unwrap();
+                    let l: Value = self.stack.pop().unwrap();
+
+                    self.stack.push(Value::Bool(l.is_equal(&r)));
+                }
+                OpCode::NotEqual => {
+                    let r: Value = self.stack.pop().unwrap();
+                    let l: Value = self.stack.pop().unwrap();
+
+                    self.stack.push(Value::Bool(!l.is_equal(&r)));
+                }
+                OpCode::Greater => {
+                    let (l, r) = self.number_operands()?;
+                    self.stack.push(Value::Bool(l > r));
+                }
+                OpCode::GreaterEqual => {
+                    let (l, r) = self.number_operands()?;
+                    self.stack.push(Value::Bool(l >= r));
+                }
+                OpCode::Less => {
+                    let (l, r) = self.number_operands()?;
+                    self.stack.push(Value::Bool(l < r));
+                }
+                OpCode::LessEqual => {
+                    let (l, r) = self.number_operands()?;
+                    self.stack.push(Value::Bool(l <= r));
+                }
+                OpCode::Add => match (self.peek(1), self.peek(0)) {
+                    (Value::Str(l), Value::Str(r)) => {
+                        let s: String = format!("{}{}", l, r);
+
+                        self.stack.truncate(self.stack.len() - 2);
+                        self.stack.push(Value::Str(Rc::from(s.as_str())));
+                    }
+                    (Value::Str(_), _) => return Err(self.error("Expect String".to_string())),
+                    _ => {
+                        let (l, r) = self.number_operands()?;
+                        self.stack.push(Value::Number(l + r));
+                    }
+                },
+                OpCode::Subtract => {
+                    let (l, r) = self.number_operands()?;
+                    self.stack.push(Value::Number(l - r));
+                }
+                OpCode::Multiply => {
+                    let (l, r) = self.number_operands()?;
+                    self.stack.push(Value::Number(l * r));
+                }
+                OpCode::Divide => {
+                    let (l, r) = self.number_operands()?;
+                    self.stack.push(Value::Number(l / r));
+                }
+                OpCode::Modulo => {
+                    let (l, r) = self.number_operands()?;
+                    self.stack.push(Value::Number(builtins::modulo(l, r)));
+                }
+                OpCode::FloorDivide => {
+                    let (l, r) = self.number_operands()?;
+                    self.stack.push(Value::Number(builtins::floor_divide(l, r)));
+                }
+                OpCode::Power => {
+                    let (l, r) = self.number_operands()?;
+                    self.stack.push(Value::Number(l.powf(r)));
+                }
+                OpCode::Not => {
+                    let value: Value = self.stack.pop().unwrap();
+                    self.stack.push(Value::Bool(!value.is_truthy()));
+                }
+                OpCode::Stringify => {
+                    let value: Value = self.stack.pop().unwrap();
+                    self.stack.push(Value::Str(Rc::from(value.to_string())));
+                }
+                OpCode::Negate => match *self.peek(0) {
+                    Value::Number(n) => {
+                        self.stack.pop();
+                        self.stack.push(Value::Number(-n));
+                    }
+                    _ => return Err(self.error("Expect number".to_string())),
+                },
+                OpCode::Print => {
+                    let value: Value = self.stack.pop().unwrap();
+                    let _ = writeln!(self.out, "{}", value);
+                }
+                OpCode::Jump(target) => self.frame_mut().ip = target as usize,
+                OpCode::JumpIfFalse(target) => {
+                    if !self.peek(0).is_truthy() {
+                        self.frame_mut().ip = target as usize;
+                    }
+                }
+                OpCode::Call(argc) => {
+                    let callee: Value = self.peek(argc as usize).clone();
+                    self.call_value(callee, argc as usize)?;
+                }
+                OpCode::Closure(idx) => {
+                    let proto: Rc<Proto> = self.frame().closure.proto.chunk.proto(idx);
+
+                    let mut upvalues: Vec<Rc<RefCell<Upvalue>>> = Vec::new();
+                    for &(is_local, index) in proto.upvalues.iter() {
+                        let upvalue: Rc<RefCell<Upvalue>> = if is_local {
+                            self.capture_upvalue(self.frame().slots + index as usize)
+                        } else {
+                            self.frame().closure.upvalues[index as usize].clone()
+                        };
+                        upvalues.push(upvalue);
+                    }
+
+                    let globals: Rc<RefCell<Environment>> = self.frame().closure.globals.clone();
+                    self.stack.push(Value::Closure(Rc::new(Closure {
+                        proto,
+                        upvalues,
+                        globals,
+                    })));
+                }
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.stack.pop();
+                }
+                OpCode::Return => {
+                    let result: Value = self.stack.pop().unwrap();
+                    let frame: CallFrame = self.frames.pop().unwrap();
+                    self.close_upvalues(frame.slots);
+                    self.stack.truncate(frame.slots);
+
+                    if self.frames.len() == base {
+                        return Ok(());
+                    }
+                    self.stack.push(result);
+                }
+                OpCode::PushCatch(target) | OpCode::PushFinally(target) => {
+                    self.handlers.push(Handler {
+                        frames: self.frames.len(),
+                        stack: self.stack.len(),
+                        target: target as usize,
+                        catch: matches!(op, OpCode::PushCatch(_)),
+                    });
+                }
+                OpCode::Import(idx) => {
+                    let path: Rc<str> = self.read_string(idx);
+                    let module: Value = self.import(&path)?;
+                    self.stack.push(module);
+                }
+                OpCode::PopHandler => {
+                    self.handlers.pop();
+                }
+                OpCode::Throw => {
+                    let value: Value = self.stack.pop().unwrap();
+
+                    return Err(builtins::throw(value, self.token()));
+                }
+                OpCode::Rethrow => {
+                    self.stack.pop();
+                    let slot: usize = self.stack.len();
+                    self.drop_pending(slot + 1);
+
+                    return Err(self.pending.pop().unwrap().1);
+                }
+                OpCode::Class(idx) => {
+                    let name: Rc<str> = self.read_string(idx);
+
+                    self.stack.push(Value::Class(Rc::new(Class {
+                        name: name.to_string(),
+                        methods: RefCell::new(HashMap::new()),
+                    })));
+                }
+                OpCode::Inherit => {
+                    let superclass: Rc<Class> = match *self.peek(1) {
+                        Value::Class(ref class) => class.clone(),
+                        _ => return Err(self.error("Superclass must be a class".to_string())),
+                    };
+
+                    if let Value::Class(subclass) = self.stack.pop().unwrap() {
+                        let methods = superclass.methods.borrow().clone();
+                        subclass.methods.borrow_mut().extend(methods);
+                    }
+                }
+                OpCode::Method(idx) => {
+                    let name: Rc<str> = self.read_string(idx);
+                    let method: Value = self.stack.pop().unwrap();
+
+                    if let Value::Class(ref class) = *self.peek(0) {
+                        class.methods.borrow_mut().insert(name.to_string(), method);
+                    }
+                }
+            }
+        }
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().unwrap()
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn peek(&self, distance: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    fn import(&mut self, path: &str) -> Result<Value, Exception> {
+        let token: Token = self.token();
+        let file: PathBuf = self.modules.resolve(path);
+        if let Some(module) = self.modules.get(&file) {
+            return Ok(module);
+        }
+
+        let statements: Vec<Stmt> = self.modules.begin(file.clone(), &token)?;
+        let proto: Rc<Proto> = match Compiler::new().compile(&statements) {
+            Ok(proto) => proto,
+            Err(errors) => return Err(self.modules.fail(&token, Error::Compile(errors))),
+        };
+
+        let globals: Rc<RefCell<Environment>> =
+            Rc::new(RefCell::new(builtins::globals(&self.natives)));
+        let closure: Rc<Closure> = Rc::new(Closure {
+            proto,
+            upvalues: Vec::new(),
+            globals: globals.clone(),
+        });
+
+        let base: usize = self.frames.len();
+        self.stack.push(Value::Closure(closure.clone()));
+        let res: Result<(), Exception> = self.call(closure, 0).and_then(|_| self.run(base));
+
+        match res {
+            Ok(()) => {
+                let module: Value = Value::Module(Rc::new(Module {
+                    name: module::name(&file),
+                    globals,
+                }));
+                self.modules.finish(Some(module.clone()));
+
+                Ok(module)
+            }
+            Err(e) => {
+                self.modules.finish(None);
+                Err(e)
+            }
+        }
+    }
+
+    fn read_string(&self, idx: u16) -> Rc<str> {
+        match *self.frame().closure.proto.chunk.constant(idx) {
+            Value::Str(ref s) => s.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn number_operands(&mut self) -> Result<(f64, f64), Exception> {
+        let r: f64 = match *self.peek(0) {
+            Value::Number(n) => n,
+            _ => return Err(self.error("Expect number".to_string())),
+        };
+        let l: f64 = match *self.peek(1) {
+            Value::Number(n) => n,
+            _ => return Err(self.error("Expect number".to_string())),
+        };
+
+        self.stack.truncate(self.stack.len() - 2);
+        Ok((l, r))
+    }
+
+    fn call_value(&mut self, callee: Value, argc: usize) -> Result<(), Exception> {
+        match callee {
+            Value::Closure(closure) => self.call(closure, argc),
+            Value::Class(class) => {
+                let slot: usize = self.stack.len() - argc - 1;
+                self.stack[slot] = Value::Instance(Rc::new(Instance {
+                    class: class.clone(),
+                    fields: RefCell::new(HashMap::new()),
+                }));
+
+                match class.find_method("init") {
+                    Some(Value::Closure(init)) => self.call(init, argc),
+                    _ => self.check_arity(0, argc),
+                }
+            }
+            Value::Native(native) => {
+                self.check_arity(native.arity, argc)?;
+
+                let args: Vec<Value> = self.stack.split_off(self.stack.len() - argc);
+                let value: Value = (native.fun)(&args).map_err(|msg| self.error(msg))?;
+
+                self.stack.pop();
+                self.stack.push(value);
+                Ok(())
+            }
+            Value::BoundMethod(bound) => {
+                let slot: usize = self.stack.len() - argc - 1;
+                self.stack[slot] = bound.receiver.clone();
+
+                self.call(bound.method.clone(), argc)
+            }
+            _ => Err(self.error("Can only call functions and classes".to_string())),
+        }
+    }
+
+    fn call(&mut self, closure: Rc<Closure>, argc: usize) -> Result<(), Exception> {
+        self.check_arity(closure.proto.arity, argc)?;
+
+        if self.frames.len() >= FRAMES_MAX {
+            return Err(self.error("Stack overflow".to_string()));
+        }
+
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            slots: self.stack.len() - argc - 1,
+        });
+        Ok(())
+    }
+
+    fn check_arity(&self, arity: usize, argc: usize) -> Result<(), Exception> {
+        if arity != argc {
+            return Err(self.error(format!("Expected {} arguments but got {}", arity, argc)));
+        }
+
+        Ok(())
+    }
+
+    fn bind_method(&mut self, class: &Class, name: &str) -> Result<(), Exception> {
+        let method: Rc<Closure> = match class.find_method(name) {
+            Some(Value::Closure(method)) => method,
+            _ => return Err(self.error(format!("Undefined property '{}'", name))),
+        };
+
+        let receiver: Value = self.stack.pop().unwrap();
+        self.stack.push(Value::BoundMethod(Rc::new(BoundMethod {
+            receiver,
+            method,
+        })));
+
+        Ok(())
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        for upvalue in self.open_upvalues.iter() {
+            if let Upvalue::Open(s) = *upvalue.borrow() {
+                if s == slot {
+                    return upvalue.clone();
+                }
+            }
+        }
+
+        let upvalue: Rc<RefCell<Upvalue>> = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        self.open_upvalues.push(upvalue.clone());
+
+        upvalue
+    }
+
+    fn close_upvalues(&mut self, last: usize) {
+        let stack: &Vec<Value> = &self.stack;
+
+        self.open_upvalues.retain(|upvalue| {
+            let slot: usize = match *upvalue.borrow() {
+                Upvalue::Open(slot) => slot,
+                Upvalue::Closed(_) => return false,
+            };
+
+            if slot >= last {
+                *upvalue.borrow_mut() = Upvalue::Closed(stack[slot].clone());
+                return false;
+            }
+
+            true
+        });
+    }
+
+    fn undefined_var(&self, name: &str) -> Exception {
+        self.error(format!("Undefined var '{}'", name))
+    }
+
+    fn error(&self, msg: String) -> Exception {
+        Exception::new(msg, self.token())
+    }
+
+    fn token(&self) -> Token {
+        let frame: &CallFrame = self.frame();
+
+        frame
+            .closure
+            .proto
+            .chunk
+            .token(frame.ip - 1)
+            .cloned()
+            .unwrap()
+    }
+}
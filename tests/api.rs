@@ -0,0 +1,262 @@
+mod common;
+
+use common::Buffer;
+use rlox::{Backend, Error, Lox, Value};
+use std::io;
+
+const BACKENDS: [Backend; 2] = [Backend::TreeWalker, Backend::Vm];
+
+fn lox(backend: Backend) -> (Lox, Buffer) {
+    let out: Buffer = Buffer::default();
+    let mut lox: Lox = Lox::new(backend);
+    lox.set_output(out.clone());
+    lox.set_diagnostics(io::sink());
+
+    (lox, out)
+}
+
+#[test]
+fn globals_set_by_the_host_are_visible_to_scripts() {
+    for backend in BACKENDS {
+        let (mut lox, out) = lox(backend);
+        lox.set_global("n", 5);
+        lox.set_global("big", 3_000_000_000u64);
+        lox.set_global("ratio", 0.5);
+        lox.set_global("name", "lox");
+        lox.set_global("flag", true);
+        lox.set_global("none", None::<f64>);
+        lox.set_global("xs", vec![1, 2, 3]);
+
+        lox.run(
+            "print n + 1; print big; print ratio; print name; print flag; print none; print xs;",
+        )
+        .unwrap();
+        assert_eq!(
+            out.contents(),
+            "6\n3000000000\n0.5\nlox\ntrue\nnil\n[1, 2, 3]\n"
+        );
+    }
+}
+
+#[test]
+fn globals_set_by_scripts_are_visible_to_the_host() {
+    for backend in BACKENDS {
+        let (mut lox, _) = lox(backend);
+        lox.run("var n = 2 * 21; var s = \"hi\"; var xs = [1, \"a\"]; var none;")
+            .unwrap();
+
+        assert_eq!(lox.get_global("n").and_then(|v| v.as_number()), Some(42.0));
+        assert_eq!(
+            lox.get_global("s").as_ref().and_then(Value::as_str),
+            Some("hi")
+        );
+        assert!(lox.get_global("none").unwrap().is_nil());
+        assert!(lox.get_global("missing").is_none());
+
+        let xs: Vec<Value> = lox.get_global("xs").and_then(|v| v.to_list()).unwrap();
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].as_number(), Some(1.0));
+        assert_eq!(xs[1].as_str(), Some("a"));
+    }
+}
+
+#[test]
+fn globals_persist_between_runs() {
+    for backend in BACKENDS {
+        let (mut lox, out) = lox(backend);
+        lox.run("var count = 1;").unwrap();
+        lox.set_global("count", 10);
+        lox.run("count = count + 1; print count;").unwrap();
+
+        assert_eq!(out.contents(), "11\n");
+        assert_eq!(
+            lox.get_global("count").and_then(|v| v.as_number()),
+            Some(11.0)
+        );
+    }
+}
+
+#[test]
+fn natives_are_called_with_their_arguments() {
+    for backend in BACKENDS {
+        let (mut lox, out) = lox(backend);
+        lox.define_native("add", 2, |args| {
+            let a: f64 = f64::try_from(args[0].clone()).map_err(|_| "Expect numbers")?;
+            let b: f64 = f64::try_from(args[1].clone()).map_err(|_| "Expect numbers")?;
+            Ok(Value::from(a + b))
+        });
+
+        lox.run("print add(1, 2); print add;").unwrap();
+        assert_eq!(out.contents(), "3\n<native fn add>\n");
+
+        let err: Error = lox.run("add(1, \"x\");").unwrap_err();
+        match err {
+            Error::Runtime(ref e) => assert_eq!(e.message(), "Expect numbers"),
+            _ => panic!("{:?}", err),
+        }
+    }
+}
+
+#[test]
+fn natives_check_their_arity() {
+    for backend in BACKENDS {
+        let (mut lox, out) = lox(backend);
+        lox.define_native("twice", 1, |args| Ok(args[0].clone()));
+        lox.define_native("none", 0, |_| Ok(Value::from(None::<f64>)));
+
+        lox.run("print twice(2); print none();").unwrap();
+        assert_eq!(out.contents(), "2\nnil\n");
+
+        for (src, msg) in [
+            ("twice();", "Expected 1 arguments but got 0"),
+            ("twice(1, 2);", "Expected 1 arguments but got 2"),
+            ("none(1);", "Expected 0 arguments but got 1"),
+        ] {
+            match lox.run(src) {
+                Err(Error::Runtime(ref e)) => assert_eq!(e.message(), msg),
+                res => panic!("{:?}", res),
+            }
+        }
+    }
+}
+
+#[test]
+fn native_errors_can_be_caught() {
+    for backend in BACKENDS {
+        let (mut lox, out) = lox(backend);
+        lox.define_native("fail", 0, |_| Err("It broke".to_string()));
+
+        lox.run("try { fail(); } catch (e) { print e.message; print e.line; }")
+            .unwrap();
+        assert_eq!(out.contents(), "It broke\n1\n");
+
+        match lox.run("print 1;\nfail();") {
+            Err(Error::Runtime(ref e)) => {
+                assert_eq!(e.message(), "It broke");
+                assert_eq!((e.line(), e.column()), (2, 6));
+            }
+            res => panic!("{:?}", res),
+        }
+    }
+}
+
+#[test]
+fn natives_can_be_redefined() {
+    for backend in BACKENDS {
+        let (mut lox, out) = lox(backend);
+        lox.define_native("answer", 0, |_| Ok(Value::from(1)));
+        lox.define_native("answer", 0, |_| Ok(Value::from(42)));
+
+        lox.run("print answer();").unwrap();
+        assert_eq!(out.contents(), "42\n");
+    }
+}
+
+#[test]
+fn values_convert_back_to_rust_types() {
+    assert_eq!(f64::try_from(Value::from(1.5)).ok(), Some(1.5));
+    assert_eq!(f64::try_from(Value::from(7u8)).ok(), Some(7.0));
+    assert_eq!(bool::try_from(Value::from(false)).ok(), Some(false));
+    assert_eq!(
+        String::try_from(Value::from("s")).ok(),
+        Some("s".to_string())
+    );
+
+    let list: Vec<Value> = Vec::try_from(Value::from(vec!["a", "b"])).unwrap();
+    assert_eq!(
+        list.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+        ["a", "b"]
+    );
+
+    assert!(Value::from(None::<bool>).is_nil());
+    assert_eq!(Value::from(-3i64).as_number(), Some(-3.0));
+}
+
+#[test]
+fn failed_conversions_hand_the_value_back() {
+    let back: Value = f64::try_from(Value::from("1")).unwrap_err();
+    assert_eq!(back.as_str(), Some("1"));
+
+    assert_eq!(
+        bool::try_from(Value::from(1)).unwrap_err().as_number(),
+        Some(1.0)
+    );
+    assert_eq!(
+        String::try_from(Value::from(true)).unwrap_err().as_bool(),
+        Some(true)
+    );
+    assert!(Vec::<Value>::try_from(Value::from(None::<f64>))
+        .unwrap_err()
+        .is_nil());
+    assert_eq!(Value::from(1).as_str(), None);
+    assert_eq!(Value::from("x").to_list().map(|l| l.len()), None);
+}
+
+#[test]
+fn errors_say_what_and_where() {
+    let (mut lox, _) = lox(Backend::TreeWalker);
+
+    match lox.run("print 1;\n  @") {
+        Err(Error::Scan(ref errors)) => {
+            assert_eq!(errors[0].message(), "Unexpected character");
+            assert_eq!((errors[0].line(), errors[0].column()), (2, 3));
+        }
+        res => panic!("{:?}", res),
+    }
+    match lox.run("var a = 1;\nprint a +;") {
+        Err(Error::Parse(ref errors)) => {
+            assert_eq!(errors[0].message(), "Expect expression");
+            assert_eq!((errors[0].line(), errors[0].column()), (2, 10));
+            assert_eq!(errors[0].file(), None);
+        }
+        res => panic!("{:?}", res),
+    }
+    match lox.run("return 1;") {
+        Err(Error::Resolve(ref errors)) => {
+            assert_eq!(errors[0].message(), "Can't return from top-level code");
+            assert_eq!((errors[0].line(), errors[0].column()), (1, 1));
+        }
+        res => panic!("{:?}", res),
+    }
+    match lox.run("print 1;\nprint -\"a\";") {
+        Err(Error::Runtime(ref e)) => {
+            assert_eq!(e.message(), "Expect number");
+            assert_eq!((e.line(), e.column()), (2, 7));
+        }
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn compile_errors_say_where() {
+    let mut lox: Lox = Lox::new(Backend::Vm);
+    lox.set_diagnostics(io::sink());
+    let locals: Vec<String> = (0..300).map(|i| format!("var x{};", i)).collect();
+    let src: String = format!("fun f() {{\n  {}\n}}", locals.join(" "));
+
+    match lox.check(&src) {
+        Err(Error::Compile(ref errors)) => {
+            assert_eq!(errors[0].message(), "Too many local variables in function");
+            assert_eq!(errors[0].line(), 2);
+        }
+        res => panic!("{:?}", res),
+    }
+}
+
+#[test]
+fn errors_can_cross_threads() {
+    fn send_sync<T: Send + Sync + 'static>() {}
+    send_sync::<Error>();
+
+    fn run(src: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut lox: Lox = Lox::new(Backend::Vm);
+        lox.set_diagnostics(io::sink());
+        lox.run(src)?;
+        Ok(())
+    }
+
+    let err = std::thread::spawn(|| run("throw \"oops\";").unwrap_err())
+        .join()
+        .unwrap();
+    assert!(err.to_string().contains("oops"), "{}", err);
+}
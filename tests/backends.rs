@@ -0,0 +1,722 @@
+mod common;
+
+use common::{error, output, run, Run};
+use rlox::{Backend, Lox};
+
+#[test]
+fn closures_keep_their_own_variables() {
+    let src: &str = "
+        fun makeCounter() {
+          var i = 0;
+          fun count() { i = i + 1; return i; }
+          return count;
+        }
+        var a = makeCounter();
+        var b = makeCounter();
+        print a(); print a(); print b();";
+
+    assert_eq!(output(src), "1\n2\n1\n");
+}
+
+#[test]
+fn closures_share_a_captured_variable() {
+    let src: &str = "
+        fun pair() {
+          var x = \"before\";
+          fun get() { return x; }
+          fun set(v) { x = v; }
+          set(\"after\");
+          return get;
+        }
+        print pair()();";
+
+    assert_eq!(output(src), "after\n");
+}
+
+#[test]
+fn closures_capture_each_loop_iteration() {
+    let src: &str = "
+        var fns = [];
+        for (var i = 0; i < 3; i++) {
+          var j = i;
+          fun f() { return j; }
+          fns.push(f);
+        }
+        print fns[0]() + fns[1]() + fns[2]();";
+
+    assert_eq!(output(src), "3\n");
+}
+
+#[test]
+fn closures_resolve_names_where_they_are_declared() {
+    let src: &str = "
+        var x = \"global\";
+        {
+          fun show() { print x; }
+          show();
+          var x = \"local\";
+          show();
+        }";
+
+    assert_eq!(output(src), "global\nglobal\n");
+}
+
+#[test]
+fn classes_with_initializers_and_super() {
+    let src: &str = "
+        class Animal {
+          init(name) { this.name = name; }
+          speak() { return this.name + \" makes a sound\"; }
+          describe() { return \"I am \" + this.name; }
+        }
+        class Dog < Animal {
+          init(name) { super.init(name); this.tricks = 0; }
+          speak() { return super.speak() + \": woof\"; }
+          learn() { this.tricks = this.tricks + 1; return this; }
+        }
+        var d = Dog(\"Rex\");
+        print d.speak();
+        print d.describe();
+        print d.learn().learn().tricks;
+        print Dog;
+        print d;";
+
+    assert_eq!(
+        output(src),
+        "Rex makes a sound: woof\nI am Rex\n2\nDog\nDog instance\n"
+    );
+}
+
+#[test]
+fn bound_methods_keep_this() {
+    let src: &str = "
+        class Box { init(v) { this.v = v; } get() { return this.v; } }
+        var get = Box(7).get;
+        print get();";
+
+    assert_eq!(output(src), "7\n");
+}
+
+#[test]
+fn super_calls_go_up_the_chain() {
+    let src: &str = "
+        class A { method() { return \"A\"; } }
+        class B < A { method() { return \"B then \" + super.method(); } }
+        class C < B {}
+        print C().method();";
+
+    assert_eq!(output(src), "B then A\n");
+}
+
+#[test]
+fn while_loops_break_and_continue() {
+    let src: &str = "
+        var i = 0;
+        while (true) {
+          i = i + 1;
+          if (i == 2) continue;
+          if (i > 4) break;
+          print i;
+        }";
+
+    assert_eq!(output(src), "1\n3\n4\n");
+}
+
+#[test]
+fn for_loops_run_the_increment_on_continue() {
+    let src: &str = "
+        for (var j = 0; j < 5; j = j + 1) {
+          if (j % 2 == 0) continue;
+          print j;
+        }";
+
+    assert_eq!(output(src), "1\n3\n");
+}
+
+#[test]
+fn break_leaves_only_the_inner_loop() {
+    let src: &str = "
+        for (var a = 0; a < 3; a++) {
+          for (var b = 0; b < 3; b++) {
+            if (b == a) break;
+            print \"${a}${b}\";
+          }
+        }";
+
+    assert_eq!(output(src), "10\n20\n21\n");
+}
+
+#[test]
+fn continue_runs_finally_blocks() {
+    let src: &str = "
+        var n = 0;
+        while (n < 3) {
+          n++;
+          try {
+            if (n == 2) continue;
+            print \"try ${n}\";
+          } finally {
+            print \"finally ${n}\";
+          }
+        }";
+
+    assert_eq!(
+        output(src),
+        "try 1\nfinally 1\nfinally 2\ntry 3\nfinally 3\n"
+    );
+}
+
+#[test]
+fn runtime_errors_stop_the_run() {
+    let (out, msg): (String, String) = error("print 1; print nope; print 2;");
+
+    assert_eq!(out, "1\n");
+    assert_eq!(msg, "[line 1] Error at 'nope': Undefined var 'nope'");
+}
+
+#[test]
+fn runtime_errors_report_the_failing_operator() {
+    let (_, msg): (String, String) = error("var a = 1;\nprint a - \"x\";");
+
+    assert_eq!(msg, "[line 2] Error at '-': Expect number");
+}
+
+#[test]
+fn runtime_errors_from_calls() {
+    let (_, msg): (String, String) = error("fun f(a, b) {}\nf(1);");
+    assert_eq!(msg, "[line 2] Error at ')': Expected 2 arguments but got 1");
+
+    let (_, msg): (String, String) = error("var x = 1;\nx();");
+    assert_eq!(
+        msg,
+        "[line 2] Error at ')': Can only call functions and classes"
+    );
+}
+
+#[test]
+fn runtime_errors_on_properties() {
+    let (_, msg): (String, String) = error("class A {}\nprint A().missing;");
+
+    assert_eq!(
+        msg,
+        "[line 2] Error at 'missing': Undefined property 'missing'"
+    );
+}
+
+#[test]
+fn uncaught_throws_are_errors() {
+    let (out, msg): (String, String) = error("print \"a\";\nthrow \"boom\";\nprint \"b\";");
+
+    assert_eq!(out, "a\n");
+    assert!(msg.starts_with("[line 2] Error at 'throw'"), "{}", msg);
+}
+
+#[test]
+fn static_errors_stop_anything_from_running() {
+    let failed: Run = run("print 1;\nprint 2 +;");
+
+    assert_eq!(failed.out, "");
+    assert_eq!(
+        failed.res.unwrap_err().lines().next(),
+        Some("[line 2] Error at ';': Expect expression")
+    );
+}
+
+#[test]
+fn jumps_past_65535_instructions() {
+    let mut src: String = "print 0;\n".repeat(35_000);
+    src += "if (true) print \"done\"; else print \"not done\";";
+
+    assert!(output(&src).ends_with("0\ndone\n"));
+}
+
+#[test]
+fn self_referencing_lists_and_maps_print() {
+    let src: &str = "
+        var l = [1, \"a\"];
+        l.push(l);
+        print l;
+        var m = {\"k\": l};
+        m[\"self\"] = m;
+        print m;
+        print \"${m}\";
+        var shared = [1];
+        print [shared, shared];";
+
+    assert_eq!(
+        output(src),
+        "[1, \"a\", [...]]\n\
+         {\"k\": [1, \"a\", [...]], \"self\": {...}}\n\
+         {\"k\": [1, \"a\", [...]], \"self\": {...}}\n\
+         [[1], [1]]\n"
+    );
+}
+
+#[test]
+fn interpolation_nests() {
+    let src: &str = "var n = 2; print \"a${n}b${\"c${n + 1}d\"}e\";";
+
+    assert_eq!(output(src), "a2bc3de\n");
+}
+
+#[test]
+fn interpolation_end_is_not_a_string_literal() {
+    let (out, msg): (String, String) = error("print \"${\"a\" +}\" \"z\";");
+
+    assert_eq!(out, "");
+    assert_eq!(msg, "[line 1] Error at '}\"': Expect expression");
+}
+
+#[test]
+fn interpolation_errors_point_inside_the_braces() {
+    let (_, msg): (String, String) = error("print \"x ${1 +} y\";");
+    assert_eq!(msg, "[line 1] Error at '} y\"': Expect expression");
+
+    let (_, msg): (String, String) = error("print \"${1 2}\";");
+    assert_eq!(
+        msg,
+        "[line 1] Error at '2': Expect '}' after interpolated expression"
+    );
+}
+
+#[test]
+fn finally_blocks_left_early_drop_their_error() {
+    let src: &str = "
+        fun f() {
+          try { throw \"outer\"; } finally {
+            var k = 0;
+            while (k < 3) { k++; try { throw \"inner\"; } finally { break; } }
+          }
+        }
+        try { f(); } catch (e) { print e; }
+        fun g() { try { throw \"x\"; } finally { return \"returned\"; } }
+        print g();
+        var i = 0;
+        while (i < 1000) { i++; try { throw i; } finally { continue; } }
+        try { throw \"last\"; } finally { print i; }";
+
+    let (out, msg): (String, String) = error(src);
+    assert_eq!(out, "outer\nreturned\n1000\n");
+    assert!(msg.contains("last"), "{}", msg);
+}
+
+#[test]
+fn errors_from_catch_blocks_run_finally_first() {
+    let src: &str = "
+        try {
+          try { throw 1; } catch (e) { throw e + 1; } finally { print \"finally\"; }
+        } catch (e) { print e; }
+        try {
+          try { throw 1; } catch (e) { throw e + 1; } finally {
+            try { throw 3; } finally { print \"inner\"; }
+          }
+        } catch (e) { print e; }";
+
+    assert_eq!(output(src), "finally\n2\ninner\n3\n");
+}
+
+#[test]
+fn unbounded_recursion_is_an_error() {
+    let (_, msg): (String, String) = error("fun r(n) { return r(n + 1); }\nr(0);");
+
+    assert_eq!(msg, "[line 1] Error at ')': Stack overflow");
+}
+
+#[test]
+fn deep_nesting_runs_up_to_a_limit() {
+    let list: String = format!("{}1{}", "[".repeat(900), "]".repeat(900));
+    assert_eq!(output(&format!("print {};", list)), list + "\n");
+
+    let block: String = format!("{}print 1;{}", "{".repeat(900), "}".repeat(900));
+    assert_eq!(output(&block), "1\n");
+
+    let (_, msg): (String, String) =
+        error(&format!("print {}1{};", "(".repeat(5000), ")".repeat(5000)));
+    assert_eq!(msg, "[line 1] Error at '(': Too much nesting");
+}
+
+#[test]
+fn right_associative_chains_count_as_nesting() {
+    let (_, msg): (String, String) = error(&format!("print {}1;", "true ? 1 : ".repeat(20_000)));
+    assert_eq!(msg, "[line 1] Error at 'true': Too much nesting");
+
+    let (_, msg): (String, String) = error(&format!("var a;\n{}1;", "a = ".repeat(20_000)));
+    assert_eq!(msg, "[line 2] Error at 'a': Too much nesting");
+}
+
+#[test]
+fn long_chains_are_freed_without_recursing() {
+    let src: String = format!("var a = 1;\nprint a{};", " + a".repeat(50_000));
+    assert_eq!(output(&src), "50001\n");
+
+    let src: &str = "
+        class Node { init(next) { this.next = next; } }
+        var list = nil;
+        var nested = [];
+        var map = {};
+        for (var i = 0; i < 50000; i++) {
+          list = Node(list);
+          nested = [nested];
+          map = {\"next\": map};
+        }
+        list = nil;
+        nested = nil;
+        map = nil;
+        print \"freed\";";
+    assert_eq!(output(src), "freed\n");
+}
+
+#[test]
+fn excerpts_are_numbered_by_where_the_span_starts() {
+    let failed: Run = run("(a\n + b) = 3;");
+    assert_eq!(
+        failed.diagnostics,
+        "[line 2] Error at '=': Invalid assignment target\n  |\n1 | (a\n  |  ^\n"
+    );
+
+    let failed: Run = run("class A { init() { return\n  1; } }");
+    assert_eq!(
+        failed.diagnostics,
+        "[line 1] Error at 'return': Can't return a value from an initializer\n  \
+         |\n\
+         2 |   1; } }\n  \
+         |   ^\n"
+    );
+}
+
+#[test]
+fn compound_assignment_on_variables_properties_and_indexes() {
+    let src: &str = "
+        var a = 10;
+        a += 5; a -= 3; a *= 2; a /= 4;
+        print a;
+        var s = \"a\"; s += \"b\"; print s;
+        class C {}
+        var c = C(); c.n = 1;
+        c.n += 9; c.n -= 2; c.n *= 3; c.n /= 4;
+        print c.n;
+        var xs = [1, 2];
+        xs[1] += 4; xs[1] -= 1; xs[1] *= 6; xs[1] /= 3;
+        print xs;
+        print a += 1;";
+
+    assert_eq!(output(src), "6\nab\n6\n[1, 10]\n7\n");
+}
+
+#[test]
+fn prefix_updates_give_the_new_value_and_postfix_the_old() {
+    let src: &str = "
+        var a = 5;
+        print a++ + ++a; print a;
+        class C {}
+        var c = C(); c.n = 1;
+        print c.n++; print ++c.n; print c.n;
+        var xs = [5, 6]; var i = 1;
+        print xs[i]--; print --xs[i]; print xs;";
+
+    assert_eq!(output(src), "12\n7\n1\n3\n3\n6\n4\n[5, 4]\n");
+}
+
+#[test]
+fn update_targets_are_evaluated_once() {
+    let src: &str = "
+        var calls = 0;
+        fun f() { calls++; return 0; }
+        class C {}
+        var c = C(); c.n = 0;
+        fun g() { calls++; return c; }
+        var xs = [1];
+        xs[f()] += 10; xs[f()]++; --xs[f()];
+        g().n += 2; g().n--;
+        print xs; print c.n; print calls;";
+
+    assert_eq!(output(src), "[11]\n1\n5\n");
+}
+
+#[test]
+fn updates_need_an_assignable_target() {
+    for src in [
+        "var a = 1; 1 += 2;",
+        "var a = 1; (a) += 1;",
+        "var a; a + 1 -= 2;",
+        "fun f() {} f()++;",
+        "++1;",
+        "var a = 1; --(a);",
+    ] {
+        let (_, err): (String, String) = error(src);
+        assert!(err.ends_with("Invalid assignment target"), "{}", err);
+    }
+
+    assert_eq!(
+        error("var a; a += 1;").1,
+        "[line 1] Error at '+=': Expect number"
+    );
+    assert_eq!(
+        error("var xs = [1]; xs[1] += 1;").1,
+        "[line 1] Error at ']': Index out of bounds"
+    );
+}
+
+#[test]
+fn conditionals_are_right_associative() {
+    let src: &str = "
+        print true ? 1 : false ? 2 : 3;
+        print false ? 1 : true ? 2 : 3;
+        print false ? 1 : false ? 2 : 3;
+        print (false ? true : false) ? 1 : 2;
+        var a; a = true ? \"x\" : \"y\"; print a;";
+
+    assert_eq!(output(src), "1\n2\n3\n2\nx\n");
+    assert_eq!(
+        error("print 1 ? 2;").1,
+        "[line 1] Error at ';': Expect ':' after then branch of conditional"
+    );
+}
+
+#[test]
+fn conditionals_only_evaluate_the_chosen_branch() {
+    let src: &str = "
+        var log = \"\";
+        fun f(s) { log += s; return s; }
+        print f(\"c\") == \"c\" ? f(\"t\") : f(\"e\");
+        print nil ? f(\"t\") : f(\"e\");
+        print false ? f(1) : true ? f(\"2\") : f(3);
+        print log;";
+
+    assert_eq!(output(src), "t\ne\n2\ncte2\n");
+}
+
+#[test]
+fn conditionals_print_as_nested_trees() {
+    let mut lox: Lox = Lox::new(Backend::TreeWalker);
+    let ast: String = lox
+        .ast("print a ? b : c ? d : e; print (a ? b : c) ? d : e; x = a ? 1 : 2;")
+        .unwrap();
+
+    assert_eq!(
+        ast,
+        "(print (?: var a var b (?: var c var d var e)))\n\
+         (print (?: (group (?: var a var b var c)) var d var e))\n\
+         (; (x = (?: var a 1 2)))"
+    );
+}
+
+#[test]
+fn powers_are_right_associative_and_bind_tighter_than_negation() {
+    let src: &str = "
+        print 2 ** 3 ** 2;
+        print (2 ** 3) ** 2;
+        print -2 ** 2;
+        print (-2) ** 2;
+        print 2 ** -1;
+        print 2 * 3 ** 2;";
+
+    assert_eq!(output(src), "512\n64\n-4\n4\n0.5\n18\n");
+    assert_eq!(
+        error("print 2 ** \"a\";").1,
+        "[line 1] Error at '**': Expect number"
+    );
+}
+
+#[test]
+fn integer_division_floors() {
+    let src: &str = "
+        print 7 ~/ 2;
+        print -7 ~/ 2;
+        print 7 ~/ -2;
+        print 7.5 ~/ 2;
+        print 10 ~/ 3 * 3;
+        print 1 ~/ 0;";
+
+    assert_eq!(output(src), "3\n-4\n-4\n3\n9\ninf\n");
+    assert_eq!(
+        error("print \"a\" ~/ 2;").1,
+        "[line 1] Error at '~/': Expect number"
+    );
+}
+
+#[test]
+fn remainders_take_the_sign_of_the_divisor() {
+    let src: &str = "
+        print -7 % 3;
+        print 7 % -3;
+        print -7 % -3;
+        print 5.5 % 2;
+        print 1 % 0;
+        var a = -7; var b = 3;
+        print (a ~/ b) * b + a % b == a;";
+
+    assert_eq!(output(src), "2\n-2\n-1\n1.5\nNaN\ntrue\n");
+    assert_eq!(
+        error("print 1 % \"a\";").1,
+        "[line 1] Error at '%': Expect number"
+    );
+}
+
+#[test]
+fn map_methods() {
+    let src: &str = "
+        var m = {\"a\": 1, 2: \"two\", true: \"yes\"};
+        print m.length();
+        print m.has(\"a\"); print m.has(\"b\");
+        print m.delete(\"a\"); print m.delete(\"a\");
+        print m.has(\"a\");
+        print m.keys(); print m.length();
+        print {}.keys(); print {}.length();";
+
+    assert_eq!(
+        output(src),
+        "3\ntrue\nfalse\ntrue\nfalse\nfalse\n[2, true]\n2\n[]\n0\n"
+    );
+    assert_eq!(
+        error("var m = {}; m.keys(1);").1,
+        "[line 1] Error at ')': Expected 0 arguments but got 1"
+    );
+}
+
+#[test]
+fn map_keys_keep_insertion_order_after_deletes() {
+    let src: &str = "
+        var m = {\"b\": 1, \"a\": 2};
+        m[\"c\"] = 3; m.delete(\"b\"); m[\"b\"] = 4;
+        print m.keys(); print m;";
+
+    assert_eq!(
+        output(src),
+        "[\"a\", \"c\", \"b\"]\n{\"a\": 2, \"c\": 3, \"b\": 4}\n"
+    );
+}
+
+#[test]
+fn map_keys_compare_by_value() {
+    let src: &str = "
+        var m = {0: \"zero\", 1: \"number\", \"1\": \"string\", true: \"bool\"};
+        print m[-0]; print m.has(-0);
+        m[-0] = \"negative\";
+        print m[0]; print m.length();
+        print m[1]; print m[\"1\"]; print m[true]; print m.has(false);";
+
+    assert_eq!(
+        output(src),
+        "zero\ntrue\nnegative\n4\nnumber\nstring\nbool\nfalse\n"
+    );
+}
+
+#[test]
+fn map_keys_must_be_numbers_strings_or_booleans() {
+    for src in [
+        "var m = {}; m[0/0] = 1;",
+        "var m = {}; print m.has(0/0);",
+        "var m = {}; m[[1]] = 1;",
+        "var m = {[1]: 2};",
+        "var m = {}; print m.delete([]);",
+        "var m = {}; m[nil] = 1;",
+    ] {
+        let (_, err): (String, String) = error(src);
+        assert!(
+            err.ends_with("Map keys must be numbers, strings or booleans"),
+            "{}",
+            err
+        );
+    }
+
+    assert_eq!(
+        error("var m = {}; print m[\"x\"];").1,
+        "[line 1] Error at ']': Undefined key 'x'"
+    );
+}
+
+#[test]
+fn resolver_errors_stop_anything_from_running() {
+    for (src, msg) in [
+        (
+            "{ var a = 1; { var a = a; } }",
+            "Error at 'a': Can't read local variable in its own initializer",
+        ),
+        (
+            "{ var a; var a; }",
+            "Error at 'a': Already a variable with this name in this scope",
+        ),
+        (
+            "fun f(a, a) {}",
+            "Error at 'a': Already a variable with this name in this scope",
+        ),
+        (
+            "return 1;",
+            "Error at 'return': Can't return from top-level code",
+        ),
+        (
+            "class A { init() { return 1; } }",
+            "Error at 'return': Can't return a value from an initializer",
+        ),
+        (
+            "fun f() { break; }",
+            "Error at 'break': Can't use 'break' outside of a loop",
+        ),
+        (
+            "continue;",
+            "Error at 'continue': Can't use 'continue' outside of a loop",
+        ),
+        (
+            "class A < A {}",
+            "Error at 'A': A class can't inherit from itself",
+        ),
+        (
+            "print super.x;",
+            "Error at 'super': Can't use 'super' outside of a class",
+        ),
+        (
+            "class A { f() { super.f(); } }",
+            "Error at 'super': Can't use 'super' in a class with no superclass",
+        ),
+        (
+            "fun f() { this; }",
+            "Error at 'this': Can't use 'this' outside of a class",
+        ),
+    ] {
+        let (out, err): (String, String) = error(&format!("print \"ran\";\n{}", src));
+        assert_eq!(out, "");
+        assert_eq!(err, format!("[line 2] {}", msg));
+    }
+}
+
+#[test]
+fn resolver_errors_are_all_reported() {
+    let run: Run = run("return;\n{ var a; var a; }\nbreak;");
+    let msg: String = run.res.unwrap_err();
+    let headers: Vec<&str> = msg.lines().filter(|l| l.starts_with('[')).collect();
+
+    assert_eq!(
+        headers,
+        [
+            "[line 1] Error at 'return': Can't return from top-level code",
+            "[line 2] Error at 'a': Already a variable with this name in this scope",
+            "[line 3] Error at 'break': Can't use 'break' outside of a loop",
+        ]
+    );
+}
+
+#[test]
+fn globals_may_be_redeclared() {
+    assert_eq!(output("var a = 1; var a = 2; print a;"), "2\n");
+    assert_eq!(
+        output("class A { init() { return; } } print A();"),
+        "A instance\n"
+    );
+}
+
+#[test]
+fn clock_gives_the_seconds_since_the_epoch() {
+    let src: &str = "
+        print clock;
+        var t = clock();
+        print t > 1700000000;
+        print clock() >= t;";
+
+    assert_eq!(output(src), "<native fn clock>\ntrue\ntrue\n");
+    assert_eq!(
+        error("clock(1);").1,
+        "[line 1] Error at ')': Expected 0 arguments but got 1"
+    );
+    assert_eq!(output("fun clock() { return 1; } print clock();"), "1\n");
+}
@@ -0,0 +1,232 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{self, Command, Output, Stdio};
+
+// What the CLI printed and the code it exited with.
+struct Exit {
+    code: i32,
+    out: String,
+    err: String,
+}
+
+fn rlox(args: &[&str], stdin: &str) -> Exit {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(args)
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The CLI may exit without reading its input, closing the pipe early.
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    let output: Output = child.wait_with_output().unwrap();
+
+    Exit {
+        code: output.status.code().unwrap(),
+        out: String::from_utf8(output.stdout).unwrap(),
+        err: String::from_utf8(output.stderr).unwrap(),
+    }
+}
+
+fn script(name: &str, src: &str) -> PathBuf {
+    let path: PathBuf = std::env::temp_dir().join(format!("rlox-{}-{}.lox", process::id(), name));
+    fs::write(&path, src).unwrap();
+
+    path
+}
+
+#[test]
+fn scripts_run_from_a_file() {
+    let path: PathBuf = script("file", "print \"hi\";");
+
+    for backend in [&[][..], &["--vm"][..]] {
+        let args: Vec<&str> = [backend, &[path.to_str().unwrap()]].concat();
+        let exit: Exit = rlox(&args, "");
+        assert_eq!((exit.code, exit.out.as_str()), (0, "hi\n"), "{}", exit.err);
+    }
+}
+
+#[test]
+fn scripts_get_the_arguments_after_them() {
+    let path: PathBuf = script("args", "print args;");
+
+    let exit: Exit = rlox(&[path.to_str().unwrap(), "a", "--vm", "-e"], "");
+    assert_eq!(exit.out, "[\"a\", \"--vm\", \"-e\"]\n");
+
+    let exit: Exit = rlox(&["-e", "print args;", "x"], "");
+    assert_eq!(exit.out, "[\"x\"]\n");
+}
+
+#[test]
+fn scripts_may_start_with_a_shebang() {
+    let path: PathBuf = script("shebang", "#!/usr/bin/env rlox\nprint 1;");
+
+    let exit: Exit = rlox(&[path.to_str().unwrap()], "");
+    assert_eq!((exit.code, exit.out.as_str()), (0, "1\n"), "{}", exit.err);
+}
+
+#[test]
+fn code_runs_from_the_command_line() {
+    let exit: Exit = rlox(&["--vm", "-e", "print 1 + 2;"], "");
+    assert_eq!((exit.code, exit.out.as_str()), (0, "3\n"));
+}
+
+#[test]
+fn scripts_run_from_stdin() {
+    let exit: Exit = rlox(&["-"], "print \"piped\";");
+    assert_eq!((exit.code, exit.out.as_str()), (0, "piped\n"));
+
+    let exit: Exit = rlox(&["run", "-", "x"], "print args;");
+    assert_eq!(exit.out, "[\"x\"]\n");
+}
+
+#[test]
+fn no_script_and_piped_stdin_runs_stdin() {
+    let exit: Exit = rlox(&[], "print 1;");
+    assert_eq!((exit.code, exit.out.as_str()), (0, "1\n"));
+
+    let exit: Exit = rlox(&[], "print -nil;");
+    assert_eq!(exit.code, 70);
+}
+
+#[test]
+fn the_prompt_needs_a_terminal() {
+    let exit: Exit = rlox(&["repl"], "print 1;");
+
+    assert_eq!(exit.code, 64);
+    assert_eq!(exit.out, "");
+    assert!(
+        exit.err.starts_with("'repl' needs stdin to be a terminal"),
+        "{}",
+        exit.err
+    );
+}
+
+#[test]
+fn bad_usage_exits_with_64() {
+    for args in [
+        &["--bogus"][..],
+        &["-e"],
+        &["check"],
+        &["repl", "x.lox"],
+        &["check", "-e", "print 1;", "x"],
+    ] {
+        let exit: Exit = rlox(args, "");
+        assert_eq!(exit.code, 64, "{:?}", args);
+        assert!(exit.err.contains("Usage: rlox"), "{:?}", args);
+    }
+
+    let exit: Exit = rlox(&["--help"], "");
+    assert_eq!(exit.code, 0);
+    assert!(exit.out.starts_with("Usage: rlox"));
+}
+
+#[test]
+fn static_errors_exit_with_65() {
+    let exit: Exit = rlox(&["-e", "print 1;\nprint 1 +;"], "");
+
+    assert_eq!(exit.code, 65);
+    assert_eq!(exit.out, "");
+    assert!(
+        exit.err
+            .starts_with("[line 2] Error at ';': Expect expression"),
+        "{}",
+        exit.err
+    );
+}
+
+#[test]
+fn missing_scripts_exit_with_66() {
+    let exit: Exit = rlox(&["no-such-script.lox"], "");
+
+    assert_eq!(exit.code, 66);
+    assert!(
+        exit.err.starts_with("Could not read 'no-such-script.lox'"),
+        "{}",
+        exit.err
+    );
+}
+
+#[test]
+fn runtime_errors_exit_with_70() {
+    for backend in [&[][..], &["--vm"][..]] {
+        let args: Vec<&str> = [backend, &["-e", "print 1; print -\"a\";"]].concat();
+        let exit: Exit = rlox(&args, "");
+
+        assert_eq!(exit.code, 70);
+        assert_eq!(exit.out, "1\n");
+        assert!(
+            exit.err.starts_with("[line 1] Error at '-'"),
+            "{}",
+            exit.err
+        );
+    }
+}
+
+#[test]
+fn check_reports_errors_without_running() {
+    let exit: Exit = rlox(&["check", "-e", "print 1; print -\"a\";"], "");
+    assert_eq!(
+        (exit.code, exit.out.as_str(), exit.err.as_str()),
+        (0, "", "")
+    );
+
+    let exit: Exit = rlox(&["check", "-"], "print 1;\nreturn;");
+    assert_eq!((exit.code, exit.out.as_str()), (65, ""));
+    assert!(
+        exit.err.starts_with("[line 2] Error at 'return'"),
+        "{}",
+        exit.err
+    );
+
+    let locals: Vec<String> = (0..300).map(|i| format!("var x{};", i)).collect();
+    let src: String = format!("fun f() {{ {} }}", locals.join(" "));
+    assert_eq!(rlox(&["check", "-e", &src], "").code, 0);
+    assert_eq!(rlox(&["--vm", "check", "-e", &src], "").code, 65);
+}
+
+#[test]
+fn tokens_and_ast_are_printed_instead_of_running() {
+    let exit: Exit = rlox(&["--tokens", "-e", "print 1;"], "");
+    assert_eq!(
+        (exit.code, exit.out.as_str()),
+        (
+            0,
+            "1:1 Print print\n1:7 Number 1\n1:8 Semicolon ;\n1:9 Eof\n"
+        )
+    );
+
+    let exit: Exit = rlox(&["--ast", "-e", "var a = 1 + 2 * 3; print a;"], "");
+    assert_eq!(
+        (exit.code, exit.out.as_str()),
+        (0, "(var a (+ 1 (* 2 3)))\n(print var a)\n")
+    );
+
+    let exit: Exit = rlox(&["--ast", "-e", "print 1 +;"], "");
+    assert_eq!((exit.code, exit.out.as_str()), (65, ""));
+
+    let exit: Exit = rlox(&["--tokens", "-e", "print @;"], "");
+    assert_eq!((exit.code, exit.out.as_str()), (65, ""));
+}
+
+#[test]
+fn every_scan_error_is_reported() {
+    for backend in [&[][..], &["--vm"][..]] {
+        let args: Vec<&str> = [backend, &["-"]].concat();
+        let exit: Exit = rlox(&args, "print 1;\nvar a = @;\nvar b = \"\\q\";\n  # \"x");
+        let headers: Vec<&str> = exit.err.lines().filter(|l| l.starts_with('[')).collect();
+
+        assert_eq!((exit.code, exit.out.as_str()), (65, ""));
+        assert_eq!(
+            headers,
+            [
+                "[line 2] Error at column 9: Unexpected character",
+                "[line 3] Error at column 10: Invalid escape sequence '\\q'",
+                "[line 4] Error at column 3: Unexpected character",
+                "[line 4] Error at column 5: Unterminated string",
+            ]
+        );
+    }
+}
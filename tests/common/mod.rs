@@ -0,0 +1,83 @@
+// Each test crate uses its own subset of these helpers.
+#![allow(dead_code)]
+
+use rlox::{Backend, Lox};
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+#[derive(Clone, Default)]
+pub struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+impl Buffer {
+    pub fn contents(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// What a run returned, printed and reported.
+#[derive(Debug, PartialEq)]
+pub struct Run {
+    pub res: Result<(), String>,
+    pub out: String,
+    pub diagnostics: String,
+}
+
+pub fn run_on(backend: Backend, src: &str, path: Option<&Path>) -> Run {
+    let (out, diagnostics): (Buffer, Buffer) = (Buffer::default(), Buffer::default());
+    let mut lox: Lox = Lox::new(backend);
+    lox.set_output(out.clone());
+    lox.set_diagnostics(diagnostics.clone());
+    if let Some(path) = path {
+        lox.set_path(path);
+    }
+
+    let res: Result<(), String> = lox.run(src).map_err(|e| e.to_string());
+
+    Run {
+        res,
+        out: out.contents(),
+        diagnostics: diagnostics.contents(),
+    }
+}
+
+// Runs `src` on both backends and checks they agree, since the tree-walker is
+// the reference the VM is held to.
+pub fn run(src: &str) -> Run {
+    run_at(src, None)
+}
+
+// Like `run`, with imports resolved relative to `path`.
+pub fn run_at(src: &str, path: Option<&Path>) -> Run {
+    let tree_walker: Run = run_on(Backend::TreeWalker, src, path);
+    let vm: Run = run_on(Backend::Vm, src, path);
+
+    assert_eq!(tree_walker, vm, "backends disagree on:\n{}", src);
+    tree_walker
+}
+
+pub fn output(src: &str) -> String {
+    let run: Run = run(src);
+    assert_eq!(run.res, Ok(()), "{}", run.diagnostics);
+
+    run.out
+}
+
+// The output up to the error, and the error's first line.
+pub fn error(src: &str) -> (String, String) {
+    let run: Run = run(src);
+    let msg: String = run.res.expect_err("expected an error");
+
+    (run.out, msg.lines().next().unwrap_or_default().to_string())
+}
@@ -0,0 +1,166 @@
+mod common;
+
+use common::{run_at, Buffer, Run};
+use rlox::{Backend, Error, Lox, Value};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process;
+
+// Writes `files` into a directory of their own and returns the path of the
+// main script in it.
+fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir: PathBuf = std::env::temp_dir().join(format!("rlox-{}-{}", process::id(), name));
+    fs::create_dir_all(&dir).unwrap();
+    for (file, src) in files {
+        fs::write(dir.join(file), src).unwrap();
+    }
+
+    dir.join("main.lox")
+}
+
+fn run_main(name: &str, main: &str, files: &[(&str, &str)]) -> Run {
+    let path: PathBuf = project(name, files);
+    fs::write(&path, main).unwrap();
+
+    run_at(main, Some(&path))
+}
+
+fn first_line(run: Run) -> String {
+    let msg: String = run.res.expect_err("expected an error");
+
+    msg.lines().next().unwrap().to_string()
+}
+
+#[test]
+fn modules_export_their_globals() {
+    let run: Run = run_main(
+        "exports",
+        "import \"counter.lox\" as c;\nprint c.inc(); print c.inc(); print c.count;",
+        &[(
+            "counter.lox",
+            "var count = 0;\nfun inc() { count = count + 1; return count; }",
+        )],
+    );
+
+    assert_eq!(run.res, Ok(()));
+    assert_eq!(run.out, "1\n2\n2\n");
+}
+
+#[test]
+fn runtime_errors_in_modules_name_the_file() {
+    let run: Run = run_main(
+        "runtime",
+        "import \"rt.lox\" as r;",
+        &[("rt.lox", "var ok = 1;\nprint nope;\n")],
+    );
+
+    assert_eq!(
+        first_line(run),
+        "[rt.lox:2] Error at 'nope': Undefined var 'nope'"
+    );
+}
+
+#[test]
+fn errors_in_module_functions_name_the_file() {
+    let run: Run = run_main(
+        "function",
+        "import \"fn.lox\" as m;\nm.f();",
+        &[("fn.lox", "fun f() {\n  return missing;\n}\n")],
+    );
+
+    assert_eq!(
+        first_line(run),
+        "[fn.lox:2] Error at 'missing': Undefined var 'missing'"
+    );
+}
+
+#[test]
+fn static_errors_in_modules_name_the_file() {
+    let run: Run = run_main(
+        "static",
+        "print 1;\nimport \"syn.lox\" as s;",
+        &[("syn.lox", "var x = 1;\nvar = 2;\n")],
+    );
+
+    assert_eq!(run.out, "1\n");
+    assert_eq!(
+        first_line(run),
+        "[syn.lox:2] Error at '=': Expect variable name"
+    );
+}
+
+#[test]
+fn static_errors_in_modules_are_static_errors() {
+    let path: PathBuf = project("kind", &[("bad.lox", "print (;\n")]);
+
+    for backend in [Backend::TreeWalker, Backend::Vm] {
+        let mut lox: Lox = Lox::new(backend);
+        lox.set_diagnostics(io::sink());
+        lox.set_path(&path);
+
+        let res: Result<(), Error> = lox.run("import \"bad.lox\" as b;");
+        assert!(matches!(res, Err(Error::Parse(_))), "{:?}", res);
+    }
+}
+
+#[test]
+fn failed_imports_can_be_caught() {
+    let run: Run = run_main(
+        "caught",
+        "try { import \"bad.lox\" as b; } catch (e) { print e.message; }",
+        &[("bad.lox", "print (;\n")],
+    );
+
+    assert_eq!(run.res, Ok(()));
+    assert_eq!(run.out, "Module 'bad.lox' has errors\n");
+}
+
+#[test]
+fn import_cycles_name_the_file() {
+    let run: Run = run_main(
+        "cycle",
+        "import \"c1.lox\" as c;",
+        &[
+            ("c1.lox", "import \"c2.lox\" as c2;\n"),
+            ("c2.lox", "print \"c2\";\nimport \"c1.lox\" as c1;\n"),
+        ],
+    );
+
+    assert_eq!(run.out, "c2\n");
+    assert_eq!(
+        first_line(run),
+        "[c2.lox:2] Error at '\"c1.lox\"': Import cycle: c1.lox -> c2.lox -> c1.lox"
+    );
+}
+
+#[test]
+fn modules_see_natives_the_host_defined() {
+    let path: PathBuf = project(
+        "natives",
+        &[(
+            "lib.lox",
+            "var n = double(21);\nfun later() { return triple(2); }",
+        )],
+    );
+
+    for backend in [Backend::TreeWalker, Backend::Vm] {
+        let out: Buffer = Buffer::default();
+        let mut lox: Lox = Lox::new(backend);
+        lox.set_output(out.clone());
+        lox.set_diagnostics(io::sink());
+        lox.set_path(&path);
+        lox.define_native("double", 1, |args| {
+            let n: f64 = f64::try_from(args[0].clone()).map_err(|_| "Expect a number")?;
+            Ok(Value::from(n * 2.0))
+        });
+        lox.define_native("triple", 1, |args| {
+            let n: f64 = f64::try_from(args[0].clone()).map_err(|_| "Expect a number")?;
+            Ok(Value::from(n * 3.0))
+        });
+
+        lox.run("import \"lib.lox\" as lib; print lib.n; print lib.later(); print lib.clock;")
+            .unwrap();
+        assert_eq!(out.contents(), "42\n6\n<native fn clock>\n");
+    }
+}
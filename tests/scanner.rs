@@ -0,0 +1,116 @@
+use rlox::{Backend, Error, Lox};
+use std::io;
+
+// Every scan error in `src` as its line, column and message.
+fn scan_errors(src: &str) -> Vec<(usize, usize, String)> {
+    let mut lox: Lox = Lox::new(Backend::TreeWalker);
+    lox.set_diagnostics(io::sink());
+
+    match lox.tokens(src) {
+        Err(Error::Scan(errors)) => errors
+            .iter()
+            .map(|e| (e.line(), e.column(), e.message().to_string()))
+            .collect(),
+        res => panic!("expected scan errors, got {:?}", res),
+    }
+}
+
+#[test]
+fn invalid_escapes_point_at_the_backslash() {
+    assert_eq!(
+        scan_errors("print 1;\nprint \"a\\qb\";"),
+        [(2, 9, "Invalid escape sequence '\\q'".to_string())]
+    );
+}
+
+#[test]
+fn bad_unicode_escapes_say_what_is_wrong() {
+    let errors: Vec<(usize, usize, String)> = scan_errors(
+        "print \"\\u{110000}\" + \"\\u{}\" + \"\\u12\" + \"\\u{1234567}\" + \"\\u{12\";",
+    );
+
+    assert_eq!(
+        errors,
+        [
+            (1, 8, "Invalid unicode code point".to_string()),
+            (
+                1,
+                23,
+                "Unicode escape must have 1 to 6 hex digits".to_string()
+            ),
+            (1, 32, "Expect '{' after '\\u'".to_string()),
+            (
+                1,
+                41,
+                "Unicode escape must have 1 to 6 hex digits".to_string()
+            ),
+            (1, 57, "Unterminated unicode escape".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn invalid_escapes_are_underlined() {
+    let mut lox: Lox = Lox::new(Backend::TreeWalker);
+    lox.set_diagnostics(io::sink());
+
+    let err: Error = lox.tokens("var s = \"\\u{d800}\";").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "[line 1] Error at column 10: Invalid unicode code point\n  \
+         |\n\
+         1 | var s = \"\\u{d800}\";\n  \
+         |          ^^^^^^^^"
+    );
+}
+
+#[test]
+fn unterminated_block_comments_point_at_where_they_open() {
+    assert_eq!(
+        scan_errors("print 1;\n  /* a\n /* b */\nprint 2;"),
+        [(2, 3, "Unterminated block comment".to_string())]
+    );
+    assert_eq!(
+        scan_errors("print 1; /* é */ /*"),
+        [(1, 18, "Unterminated block comment".to_string())]
+    );
+}
+
+#[test]
+fn closed_block_comments_nest() {
+    let mut lox: Lox = Lox::new(Backend::TreeWalker);
+    let tokens: String = lox.tokens("/* a /* b */ c */ x\n/*\n*/ y").unwrap();
+
+    assert_eq!(tokens, "1:19 Identifier x\n3:4 Identifier y\n3:5 Eof");
+}
+
+#[test]
+fn scanning_carries_on_past_errors() {
+    assert_eq!(
+        scan_errors("var a = @;\nvar b = \"\\q\";\n  # \"x"),
+        [
+            (1, 9, "Unexpected character".to_string()),
+            (2, 10, "Invalid escape sequence '\\q'".to_string()),
+            (3, 3, "Unexpected character".to_string()),
+            (3, 5, "Unterminated string".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn unterminated_interpolations_point_at_where_they_open() {
+    let unterminated =
+        |line: usize, column: usize| vec![(line, column, "Unterminated interpolation".to_string())];
+
+    assert_eq!(scan_errors("print \"a ${1 + 2\";"), unterminated(1, 10));
+    assert_eq!(scan_errors("print \"a ${1 + 2"), unterminated(1, 10));
+    assert_eq!(scan_errors("print \"a ${\"b ${1}\";"), unterminated(1, 10));
+    assert_eq!(
+        scan_errors("print 1;\nprint \"é ${\"b ${1 +\n 2\";"),
+        unterminated(2, 15)
+    );
+    assert_eq!(
+        scan_errors("print \"a ${1} b"),
+        [(1, 13, "Unterminated string".to_string())]
+    );
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use common::{output, run, Run};
+use rlox::{Backend, Error, Lox};
+use std::io;
+
+fn tokens(src: &str) -> Vec<String> {
+    let mut lox: Lox = Lox::new(Backend::TreeWalker);
+    lox.set_diagnostics(io::sink());
+
+    let dump: String = lox.tokens(src).unwrap();
+    dump.lines().map(|l| l.to_string()).collect()
+}
+
+#[test]
+fn strings_keep_their_characters() {
+    assert_eq!(output("print \"café\";"), "café\n");
+    assert_eq!(output("print \"🦀🎉\";"), "🦀🎉\n");
+    assert_eq!(output("print \"日本語のテキスト\";"), "日本語のテキスト\n");
+    assert_eq!(output("print \"👨‍👩‍👧\" + \"é\";"), "👨‍👩‍👧é\n");
+}
+
+#[test]
+fn interpolation_after_multibyte_text() {
+    assert_eq!(
+        output("var n = 3; print \"🍕×${n} für ${\"日本\"}\";"),
+        "🍕×3 für 日本\n"
+    );
+}
+
+#[test]
+fn escapes_produce_multibyte_characters() {
+    assert_eq!(output("print \"\\u{e9}\\u{1F980}\\u{65E5}\";"), "é🦀日\n");
+}
+
+#[test]
+fn comments_with_multibyte_text_are_skipped() {
+    let src: &str = "// 🎉 déjà vu 日本\n/* ñ /* 🦀 */ 中文 */ print 1;";
+
+    assert_eq!(output(src), "1\n");
+}
+
+#[test]
+fn lexemes_after_multibyte_text_are_sliced_by_bytes() {
+    assert_eq!(
+        tokens("\"héllo\"; 12.5 x;"),
+        [
+            "1:1 String \"héllo\"",
+            "1:8 Semicolon ;",
+            "1:10 Number 12.5",
+            "1:15 Identifier x",
+            "1:16 Semicolon ;",
+            "1:17 Eof",
+        ]
+    );
+}
+
+#[test]
+fn identifiers_may_use_accents_and_cjk() {
+    assert_eq!(
+        output("var café = 1; var naïve = 2; print café + naïve;"),
+        "3\n"
+    );
+    assert_eq!(output("var 名前 = \"値\"; print 名前;"), "値\n");
+    assert_eq!(
+        output("fun привет(мир) { return мир; } print привет(\"ok\");"),
+        "ok\n"
+    );
+}
+
+#[test]
+fn identifiers_may_use_underscores() {
+    assert_eq!(
+        output("var _ = 1; var _x = 2; var snake_case_1 = 3; print _ + _x + snake_case_1;"),
+        "6\n"
+    );
+}
+
+#[test]
+fn identifiers_may_continue_with_combining_marks() {
+    // "é" written as "e" followed by U+0301 COMBINING ACUTE ACCENT.
+    assert_eq!(output("var cafe\u{301} = 1; print cafe\u{301};"), "1\n");
+}
+
+#[test]
+fn emoji_are_not_identifiers() {
+    let failed: Run = run("var 🦀 = 1;");
+
+    assert_eq!(failed.out, "");
+    assert!(failed
+        .res
+        .unwrap_err()
+        .starts_with("[line 1] Error at column 5: Unexpected character"));
+}
+
+#[test]
+fn identifiers_cannot_start_with_a_digit_or_mark() {
+    assert_eq!(tokens("1abc")[..2], ["1:1 Number 1", "1:2 Identifier abc"]);
+
+    let mut lox: Lox = Lox::new(Backend::TreeWalker);
+    lox.set_diagnostics(io::sink());
+    assert!(matches!(lox.tokens("\u{301}x"), Err(Error::Scan(_))));
+}
+
+#[test]
+fn errors_point_at_the_right_column_after_multibyte_text() {
+    let failed: Run = run("var s = \"日本\"; print s + 🎉;");
+
+    assert!(failed.res.is_err());
+    assert_eq!(
+        failed.diagnostics,
+        "[line 1] Error at column 25: Unexpected character\n  \
+         |\n\
+         1 | var s = \"日本\"; print s + 🎉;\n  \
+         |                         ^\n"
+    );
+}
+
+#[test]
+fn runtime_errors_after_multibyte_text_show_the_token() {
+    let failed: Run = run("var ñ = \"日\"; print ñ - 1;");
+
+    assert!(failed
+        .res
+        .unwrap_err()
+        .starts_with("[line 1] Error at '-':"));
+}